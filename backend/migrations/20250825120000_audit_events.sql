@@ -0,0 +1,19 @@
+ALTER TABLE users
+    ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE audit_events
+(
+    id            UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    actor_user_id UUID REFERENCES users (id) ON DELETE SET NULL,
+    action        VARCHAR(64) NOT NULL,
+    target_type   VARCHAR(64) NOT NULL,
+    target_id     UUID,
+    ip_address    TEXT,
+    user_agent    TEXT,
+    diff          JSONB,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_events_actor_idx ON audit_events (actor_user_id, created_at DESC);
+CREATE INDEX audit_events_target_idx ON audit_events (target_id, created_at DESC);
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at DESC);
@@ -0,0 +1,10 @@
+CREATE TABLE bookmarks
+(
+    id         UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id    UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    note_id    UUID        NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, note_id)
+);
+
+CREATE INDEX bookmarks_note_id_idx ON bookmarks (note_id);
@@ -0,0 +1,32 @@
+CREATE TABLE collections
+(
+    id            UUID PRIMARY KEY      DEFAULT gen_random_uuid(),
+    owner_user_id UUID         NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name          VARCHAR(255) NOT NULL,
+    description   TEXT,
+    is_public     BOOLEAN      NOT NULL DEFAULT FALSE,
+    share_token   UUID         NOT NULL UNIQUE DEFAULT gen_random_uuid(),
+    created_at    TIMESTAMPTZ  NOT NULL DEFAULT NOW(),
+    updated_at    TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX collections_owner_user_id_idx ON collections (owner_user_id);
+
+CREATE TABLE collection_notes
+(
+    collection_id UUID        NOT NULL REFERENCES collections (id) ON DELETE CASCADE,
+    note_id       UUID        NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    position      INT         NOT NULL,
+    added_at      TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (collection_id, note_id)
+);
+
+CREATE TABLE collection_votes
+(
+    id            UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    user_id       UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    collection_id UUID        NOT NULL REFERENCES collections (id) ON DELETE CASCADE,
+    is_upvote     BOOLEAN     NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, collection_id)
+);
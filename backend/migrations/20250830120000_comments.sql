@@ -0,0 +1,16 @@
+CREATE TABLE comments
+(
+    id                 UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    note_id            UUID        NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    author_user_id     UUID        NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    parent_comment_id  UUID REFERENCES comments (id) ON DELETE CASCADE,
+    body               TEXT        NOT NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited_at          TIMESTAMPTZ,
+    -- Deleted comments are kept so that their replies stay in place
+    deleted_at         TIMESTAMPTZ,
+    removed_by_user_id UUID REFERENCES users (id) ON DELETE SET NULL
+);
+
+CREATE INDEX comments_note_id_idx ON comments (note_id, created_at);
+CREATE INDEX comments_parent_comment_id_idx ON comments (parent_comment_id);
@@ -0,0 +1,35 @@
+CREATE TABLE courses
+(
+    id         UUID PRIMARY KEY      DEFAULT gen_random_uuid(),
+    code       VARCHAR(50)  NOT NULL UNIQUE,
+    name       VARCHAR(255) NOT NULL,
+    department VARCHAR(255),
+    credits    INT,
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE notes
+    ADD COLUMN course_id UUID REFERENCES courses (id) ON DELETE SET NULL;
+
+CREATE INDEX notes_course_id_idx ON notes (course_id);
+
+-- Normalize existing course codes ("ma 10001", "MA-10001" -> "MA10001").
+-- Codes that don't look like an institute course code are left for moderators to fix.
+UPDATE notes
+SET course_code = UPPER(REGEXP_REPLACE(course_code, '[^A-Za-z0-9]', '', 'g'))
+WHERE UPPER(REGEXP_REPLACE(course_code, '[^A-Za-z0-9]', '', 'g')) ~ '^[A-Z]{2}[0-9]{5}$';
+
+-- Seed the catalog with the most common name used for each code
+INSERT INTO courses (code, name, department)
+SELECT DISTINCT ON (course_code) course_code, TRIM(course_name), LEFT(course_code, 2)
+FROM (SELECT course_code, course_name, COUNT(*) AS uses
+      FROM notes
+      WHERE course_code ~ '^[A-Z]{2}[0-9]{5}$'
+      GROUP BY course_code, course_name) name_counts
+ORDER BY course_code, uses DESC, course_name;
+
+UPDATE notes n
+SET course_id   = c.id,
+    course_name = c.name
+FROM courses c
+WHERE n.course_code = c.code;
@@ -0,0 +1,44 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE TABLE professors
+(
+    id         UUID PRIMARY KEY      DEFAULT gen_random_uuid(),
+    name       VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ  NOT NULL DEFAULT NOW()
+);
+
+-- Lowercased spellings that resolve to a professor, including their canonical name
+CREATE TABLE professor_aliases
+(
+    alias        VARCHAR(255) PRIMARY KEY,
+    professor_id UUID NOT NULL REFERENCES professors (id) ON DELETE CASCADE
+);
+
+CREATE TABLE note_professors
+(
+    note_id      UUID NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    professor_id UUID NOT NULL REFERENCES professors (id) ON DELETE CASCADE,
+    PRIMARY KEY (note_id, professor_id)
+);
+
+CREATE INDEX note_professors_professor_id_idx ON note_professors (professor_id);
+CREATE INDEX professor_aliases_professor_id_idx ON professor_aliases (professor_id);
+CREATE INDEX professor_aliases_alias_trgm_idx ON professor_aliases USING GIN (alias gin_trgm_ops);
+
+-- Backfill from the free-text names on existing notes, merging names that only differ in case
+INSERT INTO professors (name)
+SELECT DISTINCT ON (LOWER(TRIM(pn.name))) TRIM(pn.name)
+FROM notes n
+         CROSS JOIN LATERAL UNNEST(n.professor_names) AS pn(name)
+WHERE TRIM(pn.name) <> ''
+ORDER BY LOWER(TRIM(pn.name)), TRIM(pn.name);
+
+INSERT INTO professor_aliases (alias, professor_id)
+SELECT LOWER(name), id
+FROM professors;
+
+INSERT INTO note_professors (note_id, professor_id)
+SELECT DISTINCT n.id, a.professor_id
+FROM notes n
+         CROSS JOIN LATERAL UNNEST(n.professor_names) AS pn(name)
+         JOIN professor_aliases a ON a.alias = LOWER(TRIM(pn.name));
@@ -0,0 +1,20 @@
+-- Structured metadata to tell apart notes of the same course from different terms
+CREATE TYPE semester AS ENUM ('autumn', 'spring');
+
+CREATE TYPE note_content_type AS ENUM (
+    'class_notes',
+    'tutorial_solutions',
+    'midsem',
+    'endsem',
+    'assignment'
+);
+
+-- academic_year is the year the academic session starts in, e.g. 2024 for 2024-25
+ALTER TABLE notes
+    ADD COLUMN academic_year INTEGER CHECK (academic_year BETWEEN 1951 AND 2100),
+    ADD COLUMN semester      semester,
+    ADD COLUMN content_type  note_content_type;
+
+CREATE INDEX notes_academic_year_idx ON notes (academic_year);
+CREATE INDEX notes_semester_idx ON notes (semester);
+CREATE INDEX notes_content_type_idx ON notes (content_type);
@@ -0,0 +1,23 @@
+-- Moderator-managed synonyms, e.g. "data structures" -> "dsa". Both sides are normalized tags.
+CREATE TABLE tag_synonyms
+(
+    synonym            TEXT PRIMARY KEY,
+    tag                TEXT        NOT NULL,
+    created_by_user_id UUID        REFERENCES users (id) ON DELETE SET NULL,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (synonym <> tag)
+);
+
+CREATE INDEX tag_synonyms_tag_idx ON tag_synonyms (tag);
+CREATE INDEX notes_tags_idx ON notes USING GIN (tags);
+
+-- Case-fold, trim and deduplicate the tags of existing notes, keeping their original order
+UPDATE notes n
+SET tags = ARRAY(
+        SELECT normalized.tag
+        FROM (SELECT LOWER(REGEXP_REPLACE(TRIM(t.tag), '\s+', ' ', 'g')) AS tag, t.position
+              FROM UNNEST(n.tags) WITH ORDINALITY AS t(tag, position)) normalized
+        WHERE normalized.tag <> ''
+        GROUP BY normalized.tag
+        ORDER BY MIN(normalized.position)
+    );
@@ -0,0 +1,5 @@
+-- Prefix and trigram lookups for search suggestions
+CREATE INDEX notes_course_code_pattern_idx ON notes (course_code varchar_pattern_ops);
+CREATE INDEX notes_course_name_trgm_idx ON notes USING GIN (course_name gin_trgm_ops);
+CREATE INDEX courses_code_pattern_idx ON courses (code varchar_pattern_ops);
+CREATE INDEX courses_name_trgm_idx ON courses USING GIN (name gin_trgm_ops);
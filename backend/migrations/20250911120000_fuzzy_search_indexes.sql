@@ -0,0 +1,2 @@
+-- Trigram lookups for the typo-tolerant search fallback
+CREATE INDEX notes_course_code_trgm_idx ON notes USING GIN (course_code gin_trgm_ops);
@@ -0,0 +1,24 @@
+-- SHA-256 of the uploaded PDF, hex encoded. Older notes are hashed lazily.
+ALTER TABLE notes
+    ADD COLUMN file_sha256 CHAR(64),
+    ADD COLUMN text_sketch BIGINT[];
+
+CREATE UNIQUE INDEX notes_file_sha256_idx ON notes (file_sha256);
+CREATE INDEX notes_text_sketch_idx ON notes USING GIN (text_sketch);
+
+-- Near-duplicate pairs found on upload, for moderators to merge or dismiss.
+-- `note_id` is the newer note, `duplicate_of_note_id` the one it resembles.
+CREATE TABLE note_duplicates
+(
+    id                    UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    note_id               UUID        NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    duplicate_of_note_id  UUID        NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+    similarity            REAL        NOT NULL,
+    created_at            TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    dismissed_at          TIMESTAMPTZ,
+    dismissed_by_user_id  UUID REFERENCES users (id) ON DELETE SET NULL,
+    UNIQUE (note_id, duplicate_of_note_id),
+    CHECK (note_id <> duplicate_of_note_id)
+);
+
+CREATE INDEX note_duplicates_duplicate_of_note_id_idx ON note_duplicates (duplicate_of_note_id);
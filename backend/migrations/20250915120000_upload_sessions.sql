@@ -0,0 +1,14 @@
+-- Resumable uploads: chunks are appended to a partial file until the upload is completed
+CREATE TABLE upload_sessions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    total_size BIGINT NOT NULL CHECK (total_size > 0),
+    received_size BIGINT NOT NULL DEFAULT 0 CHECK (received_size >= 0 AND received_size <= total_size),
+    metadata JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX upload_sessions_user_id_idx ON upload_sessions(user_id);
+CREATE INDEX upload_sessions_expires_at_idx ON upload_sessions(expires_at);
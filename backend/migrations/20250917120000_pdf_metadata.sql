@@ -0,0 +1,4 @@
+-- Details read from the PDF when it is uploaded
+ALTER TABLE notes
+    ADD COLUMN page_count INTEGER CHECK (page_count > 0),
+    ADD COLUMN pdf_metadata JSONB;
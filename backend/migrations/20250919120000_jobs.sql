@@ -0,0 +1,28 @@
+-- Background jobs for processing notes after they are uploaded
+CREATE TYPE job_kind AS ENUM ('render_preview', 'extract_text', 'hash_file');
+CREATE TYPE job_status AS ENUM ('pending', 'running', 'completed', 'failed');
+
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind job_kind NOT NULL,
+    note_id UUID NOT NULL REFERENCES notes(id) ON DELETE CASCADE,
+    status job_status NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 5,
+    -- When the job may next run, pushed back after each failed attempt
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_at TIMESTAMPTZ,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX jobs_run_at_idx ON jobs(run_at) WHERE status = 'pending';
+CREATE INDEX jobs_note_id_idx ON jobs(note_id);
+-- A note has at most one queued job of each kind
+CREATE UNIQUE INDEX jobs_note_id_kind_idx ON jobs(note_id, kind) WHERE status IN ('pending', 'running');
+
+-- Hash and sketch notes uploaded before duplicate detection existed
+INSERT INTO jobs (kind, note_id) SELECT 'hash_file', id FROM notes WHERE file_sha256 IS NULL;
+INSERT INTO jobs (kind, note_id) SELECT 'extract_text', id FROM notes WHERE text_sketch IS NULL;
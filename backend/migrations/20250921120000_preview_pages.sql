@@ -0,0 +1,6 @@
+-- Number of pages rendered for the page preview strip, 0 if the note only has the single preview image
+ALTER TABLE notes ADD COLUMN preview_pages INTEGER NOT NULL DEFAULT 0;
+
+-- Render the page strip for existing notes
+INSERT INTO jobs (kind, note_id) SELECT 'render_preview', id FROM notes
+ON CONFLICT (note_id, kind) WHERE status IN ('pending', 'running') DO NOTHING;
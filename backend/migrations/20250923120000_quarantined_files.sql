@@ -0,0 +1,14 @@
+-- Uploads that clamd found malware in. The file is kept under the quarantine directory for
+-- moderators to review, and no note is created for it.
+CREATE TABLE quarantined_files
+(
+    id               UUID PRIMARY KEY     DEFAULT gen_random_uuid(),
+    file_key         TEXT        NOT NULL,
+    file_sha256      CHAR(64)    NOT NULL,
+    signature        TEXT        NOT NULL,
+    uploader_user_id UUID        REFERENCES users (id) ON DELETE SET NULL,
+    course_code      VARCHAR(50) NOT NULL,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX quarantined_files_created_at_idx ON quarantined_files (created_at);
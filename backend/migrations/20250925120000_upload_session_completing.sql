@@ -0,0 +1,2 @@
+-- Set while an upload is being turned into a note, so the session isn't locked for the whole of it
+ALTER TABLE upload_sessions ADD COLUMN completing BOOLEAN NOT NULL DEFAULT FALSE;
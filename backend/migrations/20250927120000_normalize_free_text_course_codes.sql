@@ -0,0 +1,13 @@
+-- Courses outside the catalog keep their code as free text, normalized like the codes of new notes.
+-- The first catalog migration only normalized codes in the institute format.
+UPDATE notes
+SET course_code = UPPER(REGEXP_REPLACE(course_code, '[^A-Za-z0-9]', '', 'g'))
+WHERE course_code <> UPPER(REGEXP_REPLACE(course_code, '[^A-Za-z0-9]', '', 'g'))
+  AND REGEXP_REPLACE(course_code, '[^A-Za-z0-9]', '', 'g') <> '';
+
+UPDATE notes n
+SET course_id   = c.id,
+    course_name = c.name
+FROM courses c
+WHERE n.course_code = c.code
+  AND n.course_id IS NULL;
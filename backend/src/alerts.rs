@@ -0,0 +1,32 @@
+//! Alerts about problems moderators should look at right away.
+//!
+//! Alerts are always logged, and also posted to `MODERATOR_WEBHOOK_URL` as `{"text": ...}` when it
+//! is set, which Slack and Mattermost incoming webhooks accept.
+
+use serde_json::json;
+use std::time::Duration;
+
+use crate::env::EnvVars;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Alerts moderators. The webhook is called in the background, so callers aren't held up by it.
+pub fn alert_moderators(env_vars: &EnvVars, message: String) {
+    tracing::warn!("Moderator alert: {}", message);
+
+    let Some(webhook_url) = env_vars.moderator_webhook_url.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let result = reqwest::Client::new()
+            .post(&webhook_url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(&json!({ "text": message }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            tracing::error!("Failed to send moderator alert: {}", err);
+        }
+    });
+}
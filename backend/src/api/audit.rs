@@ -0,0 +1,175 @@
+use crate::api::models::CreateAuditEvent;
+use crate::api::router::RouterState;
+use crate::db::handlers::audit::insert_audit_event;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use uuid::Uuid;
+
+/// State-changing operations that are recorded in the audit log.
+#[derive(Debug, Clone, Copy)]
+pub enum AuditAction {
+    UserLogin,
+    NoteUpload,
+    NoteVote,
+    NoteBookmark,
+    NoteUnbookmark,
+    NoteMerge,
+    NoteDuplicateDismiss,
+    CollectionCreate,
+    CollectionUpdate,
+    CollectionDelete,
+    CollectionAddNote,
+    CollectionRemoveNote,
+    CollectionReorder,
+    CollectionVote,
+    CommentCreate,
+    CommentUpdate,
+    CommentDelete,
+    CommentRemove,
+    CourseImport,
+    PreviewBackfill,
+    ProfessorAliasAdd,
+    TagSynonymAdd,
+    TagSynonymRemove,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::UserLogin => "user.login",
+            AuditAction::NoteUpload => "note.upload",
+            AuditAction::NoteVote => "note.vote",
+            AuditAction::NoteBookmark => "note.bookmark",
+            AuditAction::NoteUnbookmark => "note.unbookmark",
+            AuditAction::NoteMerge => "note.merge",
+            AuditAction::NoteDuplicateDismiss => "note.duplicate_dismiss",
+            AuditAction::CollectionCreate => "collection.create",
+            AuditAction::CollectionUpdate => "collection.update",
+            AuditAction::CollectionDelete => "collection.delete",
+            AuditAction::CollectionAddNote => "collection.add_note",
+            AuditAction::CollectionRemoveNote => "collection.remove_note",
+            AuditAction::CollectionReorder => "collection.reorder",
+            AuditAction::CollectionVote => "collection.vote",
+            AuditAction::CommentCreate => "comment.create",
+            AuditAction::CommentUpdate => "comment.update",
+            AuditAction::CommentDelete => "comment.delete",
+            AuditAction::CommentRemove => "comment.remove",
+            AuditAction::CourseImport => "course.import",
+            AuditAction::PreviewBackfill => "note.preview_backfill",
+            AuditAction::ProfessorAliasAdd => "professor.alias_add",
+            AuditAction::TagSynonymAdd => "tag.synonym_add",
+            AuditAction::TagSynonymRemove => "tag.synonym_remove",
+        }
+    }
+
+    /// The kind of entity the action's `target_id` refers to.
+    pub fn target_type(&self) -> &'static str {
+        match self {
+            AuditAction::UserLogin => "user",
+            AuditAction::NoteUpload
+            | AuditAction::NoteVote
+            | AuditAction::NoteBookmark
+            | AuditAction::NoteUnbookmark
+            | AuditAction::NoteMerge
+            | AuditAction::PreviewBackfill
+            | AuditAction::NoteDuplicateDismiss => "note",
+            AuditAction::CollectionCreate
+            | AuditAction::CollectionUpdate
+            | AuditAction::CollectionDelete
+            | AuditAction::CollectionAddNote
+            | AuditAction::CollectionRemoveNote
+            | AuditAction::CollectionReorder
+            | AuditAction::CollectionVote => "collection",
+            AuditAction::CommentCreate
+            | AuditAction::CommentUpdate
+            | AuditAction::CommentDelete
+            | AuditAction::CommentRemove => "comment",
+            AuditAction::CourseImport => "course",
+            AuditAction::ProfessorAliasAdd => "professor",
+            AuditAction::TagSynonymAdd | AuditAction::TagSynonymRemove => "tag",
+        }
+    }
+}
+
+/// Client information attached to every audit event.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMeta {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// The address of the client that sent a request.
+///
+/// Behind reverse proxies the socket address is the nearest proxy's. Each proxy appends the
+/// address it received the request from to `X-Forwarded-For`, so with `trusted_proxy_hops`
+/// proxies the client is that many entries from the right; entries further left were sent by
+/// the client itself and can be forged. The socket address is used when there are no proxies or
+/// the header has too few entries.
+fn client_ip(parts: &Parts, trusted_proxy_hops: usize) -> Option<String> {
+    let forwarded = parts
+        .headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    let forwarded_ip = trusted_proxy_hops
+        .checked_sub(1)
+        .and_then(|hops| forwarded.len().checked_sub(hops + 1))
+        .and_then(|index| forwarded[index].parse::<IpAddr>().ok());
+
+    forwarded_ip
+        .or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        })
+        .map(|ip| ip.to_string())
+}
+
+impl FromRequestParts<RouterState> for RequestMeta {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &RouterState) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get("user-agent")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        Ok(Self {
+            ip_address: client_ip(parts, state.env_vars.trusted_proxy_hops),
+            user_agent,
+        })
+    }
+}
+
+/// Records an audit event. Failures are logged and never fail the request that triggered them.
+pub async fn record_event(
+    state: &RouterState,
+    actor_user_id: Option<Uuid>,
+    action: AuditAction,
+    target_id: Option<Uuid>,
+    meta: &RequestMeta,
+    diff: Option<serde_json::Value>,
+) {
+    let event = CreateAuditEvent {
+        actor_user_id,
+        action: action.as_str().to_string(),
+        target_type: action.target_type().to_string(),
+        target_id,
+        ip_address: meta.ip_address.clone(),
+        user_agent: meta.user_agent.clone(),
+        diff,
+    };
+
+    if let Err(err) = insert_audit_event(&state.db_wrapper, event).await {
+        tracing::error!("Failed to record audit event {}: {}", action.as_str(), err);
+    }
+}
@@ -7,6 +7,14 @@ pub enum AppError {
     Note(NoteError),
     User(UserError),
     Auth(AuthError),
+    Audit(AuditError),
+    Collection(CollectionError),
+    Comment(CommentError),
+    Course(CourseError),
+    Professor(ProfessorError),
+    Tag(TagError),
+    UploadSession(UploadSessionError),
+    File(FileError),
 }
 
 impl IntoResponse for AppError {
@@ -15,6 +23,14 @@ impl IntoResponse for AppError {
             AppError::Note(err) => err.into_response(),
             AppError::User(err) => err.into_response(),
             AppError::Auth(err) => err.into_response(),
+            AppError::Audit(err) => err.into_response(),
+            AppError::Collection(err) => err.into_response(),
+            AppError::Comment(err) => err.into_response(),
+            AppError::Course(err) => err.into_response(),
+            AppError::Professor(err) => err.into_response(),
+            AppError::Tag(err) => err.into_response(),
+            AppError::UploadSession(err) => err.into_response(),
+            AppError::File(err) => err.into_response(),
         }
     }
 }
@@ -89,9 +105,16 @@ impl IntoResponse for AuthError {
 #[derive(Debug)]
 pub enum NoteError {
     InvalidData(String),
+    NotFound(String),
     UploadFailed(String),
     DatabaseError(String, Box<dyn std::error::Error>),
     BadVote(String),
+    /// The uploaded file is identical to an existing note's, given with the note's id and file URL
+    Duplicate(uuid::Uuid, String),
+    /// Malware was found in the uploaded file, which was quarantined
+    Infected(String),
+    /// The server is too busy to process the upload right now
+    Unavailable(String),
 }
 
 impl From<NoteError> for AppError {
@@ -104,6 +127,7 @@ impl IntoResponse for NoteError {
     fn into_response(self) -> Response {
         let (status, error_message) = match self {
             NoteError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            NoteError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             NoteError::UploadFailed(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             NoteError::DatabaseError(msg, err) => {
                 tracing::error!("Database error: {:?}", err);
@@ -113,6 +137,269 @@ impl IntoResponse for NoteError {
                 )
             }
             NoteError::BadVote(msg) => (StatusCode::BAD_REQUEST, msg),
+            NoteError::Infected(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+            NoteError::Unavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            NoteError::Duplicate(note_id, file_url) => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(json!({
+                        "error": "This file has already been uploaded",
+                        "existing_note_id": note_id,
+                        "existing_note_url": file_url,
+                    })),
+                )
+                    .into_response();
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+pub enum AuditError {
+    InvalidData(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<AuditError> for AppError {
+    fn from(err: AuditError) -> Self {
+        AppError::Audit(err)
+    }
+}
+
+impl IntoResponse for AuditError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            AuditError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            AuditError::DatabaseError(msg, err) => {
+                tracing::error!("Audit database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum CollectionError {
+    InvalidData(String),
+    NotFound(String),
+    Forbidden(String),
+    BadVote(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<CollectionError> for AppError {
+    fn from(err: CollectionError) -> Self {
+        AppError::Collection(err)
+    }
+}
+
+impl IntoResponse for CollectionError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            CollectionError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            CollectionError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            CollectionError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            CollectionError::BadVote(msg) => (StatusCode::BAD_REQUEST, msg),
+            CollectionError::DatabaseError(msg, err) => {
+                tracing::error!("Collection database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum CommentError {
+    InvalidData(String),
+    NotFound(String),
+    Forbidden(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<CommentError> for AppError {
+    fn from(err: CommentError) -> Self {
+        AppError::Comment(err)
+    }
+}
+
+impl IntoResponse for CommentError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            CommentError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            CommentError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            CommentError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            CommentError::DatabaseError(msg, err) => {
+                tracing::error!("Comment database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum CourseError {
+    InvalidData(String),
+    NotFound(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<CourseError> for AppError {
+    fn from(err: CourseError) -> Self {
+        AppError::Course(err)
+    }
+}
+
+impl IntoResponse for CourseError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            CourseError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            CourseError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            CourseError::DatabaseError(msg, err) => {
+                tracing::error!("Course database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfessorError {
+    InvalidData(String),
+    NotFound(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<ProfessorError> for AppError {
+    fn from(err: ProfessorError) -> Self {
+        AppError::Professor(err)
+    }
+}
+
+impl IntoResponse for ProfessorError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            ProfessorError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            ProfessorError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ProfessorError::DatabaseError(msg, err) => {
+                tracing::error!("Professor database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum TagError {
+    InvalidData(String),
+    NotFound(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<TagError> for AppError {
+    fn from(err: TagError) -> Self {
+        AppError::Tag(err)
+    }
+}
+
+impl IntoResponse for TagError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            TagError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            TagError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            TagError::DatabaseError(msg, err) => {
+                tracing::error!("Tag database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum UploadSessionError {
+    InvalidData(String),
+    NotFound(String),
+    Expired(String),
+    /// The chunk doesn't start where the upload left off, given with the offset to resume from
+    OffsetMismatch(i64),
+    /// The upload is already being turned into a note
+    Completing(String),
+    TooManySessions(String),
+    UploadFailed(String),
+    DatabaseError(String, Box<dyn std::error::Error>),
+}
+
+impl From<UploadSessionError> for AppError {
+    fn from(err: UploadSessionError) -> Self {
+        AppError::UploadSession(err)
+    }
+}
+
+impl IntoResponse for UploadSessionError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            UploadSessionError::InvalidData(msg) => (StatusCode::BAD_REQUEST, msg),
+            UploadSessionError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            UploadSessionError::Expired(msg) => (StatusCode::GONE, msg),
+            UploadSessionError::OffsetMismatch(offset) => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(json!({
+                        "error": format!("Chunk offset doesn't match, resume from offset {}", offset),
+                        "offset": offset,
+                    })),
+                )
+                    .into_response();
+            }
+            UploadSessionError::Completing(msg) => (StatusCode::CONFLICT, msg),
+            UploadSessionError::TooManySessions(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
+            UploadSessionError::UploadFailed(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            UploadSessionError::DatabaseError(msg, err) => {
+                tracing::error!("Upload session database error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
+        };
+
+        (status, Json(json!({ "error": error_message }))).into_response()
+    }
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Forbidden(String),
+    Expired(String),
+    NotFound(String),
+    StorageError(String, Box<dyn std::error::Error>),
+}
+
+impl From<FileError> for AppError {
+    fn from(err: FileError) -> Self {
+        AppError::File(err)
+    }
+}
+
+impl IntoResponse for FileError {
+    fn into_response(self) -> Response {
+        let (status, error_message) = match self {
+            FileError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            FileError::Expired(msg) => (StatusCode::GONE, msg),
+            FileError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            FileError::StorageError(msg, err) => {
+                tracing::error!("File storage error: {:?}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, msg)
+            }
         };
 
         (status, Json(json!({ "error": error_message }))).into_response()
@@ -0,0 +1,424 @@
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
+use crate::api::errors::{AppError, AuditError, CourseError, NoteError, ProfessorError, TagError};
+use crate::api::handlers::tags::normalize_tag;
+use crate::api::models::{AuditEventFilter, CreateProfessorAlias, CreateTagSynonym};
+use crate::api::router::RouterState;
+use crate::catalog::{import_catalog, CatalogError, CatalogFormat};
+use crate::preview::{find_missing_previews, remove_previews, PreviewFailure};
+use crate::storage::Storage;
+use crate::db::handlers::audit::get_audit_events;
+use crate::db::handlers::jobs::enqueue_job;
+use crate::db::handlers::duplicates::{
+    dismiss_note_duplicate, get_note_duplicate, get_note_duplicates, merge_notes,
+};
+use crate::db::handlers::professors::add_professor_alias;
+use crate::db::handlers::quarantine::get_quarantined_files;
+use crate::db::handlers::tags::{add_tag_synonym, get_tag_synonyms, remove_tag_synonym};
+use crate::db::models::{JobKind, User};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+const MAX_AUDIT_EVENTS: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub actor: Option<Uuid>,
+    pub target: Option<Uuid>,
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub num: Option<usize>,
+}
+
+/// API handler to query the audit log by actor, target, action and time range.
+pub async fn list_audit_events(
+    State(state): State<RouterState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(AuditError::InvalidData("`from` must be before `to`".to_string()).into());
+        }
+    }
+
+    let filter = AuditEventFilter {
+        actor_user_id: query.actor,
+        target_id: query.target,
+        action: query.action,
+        from: query.from,
+        to: query.to,
+        limit: query.num.unwrap_or(100).min(MAX_AUDIT_EVENTS),
+    };
+
+    let events = get_audit_events(&state.db_wrapper, filter)
+        .await
+        .map_err(|err| {
+            AuditError::DatabaseError("Failed to fetch audit events".to_string(), err.into())
+        })?;
+
+    Ok((StatusCode::OK, Json(events).into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct CourseImportQuery {
+    pub dry_run: Option<bool>,
+    pub remove_missing: Option<bool>,
+}
+
+/// API handler to bulk import the course catalog. The body is CSV (`text/csv`) or JSON (`application/json`).
+pub async fn import_courses(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Query(query): Query<CourseImportQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Response), AppError> {
+    let format = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(CatalogFormat::from_content_type)
+        .ok_or_else(|| {
+            CourseError::InvalidData(
+                "Content-Type must be text/csv or application/json".to_string(),
+            )
+        })?;
+
+    let remove_missing = query.remove_missing.unwrap_or(false);
+    let report = import_catalog(
+        &state.db_wrapper,
+        &body,
+        format,
+        query.dry_run.unwrap_or(false),
+        remove_missing,
+    )
+    .await
+    .map_err(|err| match err {
+        CatalogError::Parse(_) => CourseError::InvalidData(err.to_string()),
+        CatalogError::Database(e) => {
+            CourseError::DatabaseError("Failed to import courses".to_string(), e.into())
+        }
+    })?;
+
+    if !report.invalid.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(report).into_response()));
+    }
+
+    if report.applied {
+        record_event(
+            &state,
+            Some(user.id),
+            AuditAction::CourseImport,
+            None,
+            &meta,
+            Some(json!({
+                "added": report.added.len(),
+                "changed": report.changed.len(),
+                "removed": if remove_missing { report.removed.len() } else { 0 },
+            })),
+        )
+        .await;
+    }
+
+    Ok((StatusCode::OK, Json(report).into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct PreviewBackfillQuery {
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct PreviewBackfillResponse {
+    pub dry_run: bool,
+    /// Number of notes checked
+    pub checked: usize,
+    /// Notes queued to have their previews rendered by the job workers, or that would be on a dry run
+    pub queued: Vec<Uuid>,
+    /// Notes missing previews that couldn't be queued
+    pub failed: Vec<PreviewFailure>,
+}
+
+/// API handler to queue notes without previews, or whose preview files are missing from storage,
+/// to be rendered again by the job workers. Notes are picked the same way as by the
+/// `backfill-previews` command.
+pub async fn backfill_note_previews(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Query(query): Query<PreviewBackfillQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let dry_run = query.dry_run.unwrap_or(false);
+    let (storage, paths) = (&state.env_vars.storage, &state.env_vars.paths);
+    let (checked, missing) = find_missing_previews(&state.db_wrapper, storage, paths)
+        .await
+        .map_err(|e| NoteError::DatabaseError("Failed to fetch notes".to_string(), e.into()))?;
+
+    let mut queued = Vec::new();
+    let mut failed = Vec::new();
+    for status in missing {
+        // Previews are rendered from the note's PDF, so there is no point queueing notes without one
+        let pdf_key = paths.get_note_slug(&format!("{}.pdf", status.id));
+        let result = match storage.exists(&pdf_key).await {
+            Ok(true) if dry_run => Ok(()),
+            Ok(true) => queue_preview_job(&state, status.id).await.map_err(|e| e.to_string()),
+            Ok(false) => Err("The note's PDF is missing from storage".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => queued.push(status.id),
+            Err(error) => failed.push(PreviewFailure { note_id: status.id, error }),
+        }
+    }
+
+    if !dry_run {
+        record_event(
+            &state,
+            Some(user.id),
+            AuditAction::PreviewBackfill,
+            None,
+            &meta,
+            Some(json!({ "queued": queued.len(), "failed": failed.len() })),
+        )
+        .await;
+    }
+
+    let status = if dry_run { StatusCode::OK } else { StatusCode::ACCEPTED };
+    Ok((
+        status,
+        Json(PreviewBackfillResponse { dry_run, checked, queued, failed }).into_response(),
+    ))
+}
+
+async fn queue_preview_job(state: &RouterState, note_id: Uuid) -> Result<(), sqlx::Error> {
+    let mut tx = state.db_wrapper.pool().begin().await?;
+    enqueue_job(&mut tx, note_id, JobKind::RenderPreview).await?;
+    tx.commit().await
+}
+
+/// API handler to add an alias to a professor. An alias of another professor merges that professor
+/// into this one, e.g. to fix a misspelled name that was uploaded as a new professor.
+pub async fn create_professor_alias(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Path(professor_id): Path<Uuid>,
+    Json(payload): Json<CreateProfessorAlias>,
+) -> Result<(StatusCode, Response), AppError> {
+    let alias = payload.alias.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if alias.is_empty() {
+        return Err(ProfessorError::InvalidData("Alias cannot be empty".to_string()).into());
+    }
+
+    let change = add_professor_alias(&state.db_wrapper, professor_id, &alias)
+        .await
+        .map_err(|e| ProfessorError::DatabaseError("Failed to add alias".to_string(), e.into()))?
+        .ok_or_else(|| ProfessorError::NotFound("Professor not found".to_string()))?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::ProfessorAliasAdd,
+        Some(professor_id),
+        &meta,
+        Some(json!({
+            "alias": &change.alias,
+            "merged_professor_id": change.merged_professor.as_ref().map(|p| p.id),
+        })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(change).into_response()))
+}
+
+/// API handler to list the tag synonym table.
+pub async fn list_tag_synonyms(
+    State(state): State<RouterState>,
+) -> Result<(StatusCode, Response), AppError> {
+    let synonyms = get_tag_synonyms(&state.db_wrapper)
+        .await
+        .map_err(|e| TagError::DatabaseError("Failed to fetch tag synonyms".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(synonyms).into_response()))
+}
+
+/// API handler to merge a tag into another. Notes already using the synonym are retagged.
+pub async fn create_tag_synonym(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Json(payload): Json<CreateTagSynonym>,
+) -> Result<(StatusCode, Response), AppError> {
+    let synonym = normalize_tag(&payload.synonym);
+    let tag = normalize_tag(&payload.tag);
+    if synonym.is_empty() || tag.is_empty() {
+        return Err(TagError::InvalidData("Synonym and tag cannot be empty".to_string()).into());
+    }
+    if synonym == tag {
+        return Err(TagError::InvalidData("A tag cannot be a synonym of itself".to_string()).into());
+    }
+
+    let tag_synonym = add_tag_synonym(&state.db_wrapper, &synonym, &tag, user.id)
+        .await
+        .map_err(|e| TagError::DatabaseError("Failed to add tag synonym".to_string(), e.into()))?
+        .ok_or_else(|| TagError::InvalidData(format!("'{}' is already a synonym of '{}'", tag, synonym)))?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::TagSynonymAdd,
+        None,
+        &meta,
+        Some(json!({ "synonym": &tag_synonym.synonym, "tag": &tag_synonym.tag })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(tag_synonym).into_response()))
+}
+
+/// API handler to remove a tag synonym.
+pub async fn delete_tag_synonym(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Path(synonym): Path<String>,
+) -> Result<(StatusCode, Response), AppError> {
+    let synonym = normalize_tag(&synonym);
+    let removed = remove_tag_synonym(&state.db_wrapper, &synonym)
+        .await
+        .map_err(|e| TagError::DatabaseError("Failed to remove tag synonym".to_string(), e.into()))?;
+    if !removed {
+        return Err(TagError::NotFound(format!("Tag synonym '{}' not found", synonym)).into());
+    }
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::TagSynonymRemove,
+        None,
+        &meta,
+        Some(json!({ "synonym": synonym })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+const MAX_DUPLICATES: usize = 500;
+
+#[derive(Deserialize)]
+pub struct DuplicateQuery {
+    pub include_dismissed: Option<bool>,
+    pub num: Option<usize>,
+}
+
+/// API handler to list near-duplicate notes flagged on upload.
+pub async fn list_duplicates(
+    State(state): State<RouterState>,
+    Query(query): Query<DuplicateQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let duplicates = get_note_duplicates(
+        &state.db_wrapper,
+        query.include_dismissed.unwrap_or(false),
+        query.num.unwrap_or(100).min(MAX_DUPLICATES),
+    )
+    .await
+    .map_err(|e| NoteError::DatabaseError("Failed to fetch duplicates".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(duplicates).into_response()))
+}
+
+/// API handler to merge a flagged near duplicate into the older note it resembles.
+pub async fn merge_duplicate(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Path(duplicate_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let duplicate = get_note_duplicate(&state.db_wrapper, duplicate_id)
+        .await
+        .map_err(|e| NoteError::DatabaseError("Failed to fetch duplicate".to_string(), e.into()))?
+        .ok_or_else(|| NoteError::NotFound("Duplicate not found".to_string()))?;
+
+    merge_notes(&state.db_wrapper, duplicate.duplicate_of_note_id, duplicate.note_id)
+        .await
+        .map_err(|e| NoteError::DatabaseError("Failed to merge notes".to_string(), e.into()))?;
+
+    // The merged note's files are no longer referenced
+    let (storage, paths) = (&state.env_vars.storage, &state.env_vars.paths);
+    if let Err(err) = storage.delete(&paths.get_note_slug(&format!("{}.pdf", duplicate.note_id))).await {
+        tracing::warn!("Failed to delete file of merged note {}: {}", duplicate.note_id, err);
+    }
+    remove_previews(storage, paths, duplicate.note_id).await;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::NoteMerge,
+        Some(duplicate.duplicate_of_note_id),
+        &meta,
+        Some(json!({
+            "merged_note_id": duplicate.note_id,
+            "similarity": duplicate.similarity,
+        })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+/// API handler to mark a flagged near duplicate as a false positive.
+pub async fn dismiss_duplicate(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Path(duplicate_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let duplicate = dismiss_note_duplicate(&state.db_wrapper, duplicate_id, user.id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => NoteError::NotFound("Duplicate not found".to_string()),
+            e => NoteError::DatabaseError("Failed to dismiss duplicate".to_string(), e.into()),
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::NoteDuplicateDismiss,
+        Some(duplicate.note_id),
+        &meta,
+        Some(json!({ "duplicate_of_note_id": duplicate.duplicate_of_note_id })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(duplicate).into_response()))
+}
+
+const MAX_QUARANTINED_FILES: usize = 500;
+
+#[derive(Deserialize)]
+pub struct QuarantineQuery {
+    pub num: Option<usize>,
+}
+
+/// API handler to list uploads that malware was found in, newest first.
+pub async fn list_quarantined_files(
+    State(state): State<RouterState>,
+    Query(query): Query<QuarantineQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let quarantined_files = get_quarantined_files(
+        &state.db_wrapper,
+        query.num.unwrap_or(100).min(MAX_QUARANTINED_FILES),
+    )
+    .await
+    .map_err(|e| NoteError::DatabaseError("Failed to fetch quarantined files".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(quarantined_files).into_response()))
+}
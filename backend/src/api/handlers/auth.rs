@@ -1,3 +1,4 @@
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
 use crate::api::middleware::AppClaims;
 use crate::api::errors::AppError;
 use crate::api::router::RouterState;
@@ -79,6 +80,7 @@ async fn get_google_public_keys() -> Result<HashMap<String, DecodingKey>, AppErr
 
 pub async fn google_auth_callback(
     State(state): State<RouterState>,
+    meta: RequestMeta,
     Json(payload): Json<AuthRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
     let header = decode_header(&payload.token).map_err(|e| {
@@ -96,7 +98,7 @@ pub async fn google_auth_callback(
         )
     })?;
 
-    let google_client_id = state.env_vars.google_client_id;
+    let google_client_id = state.env_vars.google_client_id.clone();
     let mut validation = Validation::new(header.alg);
     validation.set_audience(&[google_client_id]);
 
@@ -145,6 +147,8 @@ pub async fn google_auth_callback(
             crate::api::errors::AuthError::ConfigError("Failed to create JWT token".to_string())
         })?;
 
+    record_event(&state, Some(user.id), AuditAction::UserLogin, Some(user.id), &meta, None).await;
+
     // Return User and Token
     Ok(Json(json!({
         "user": user,
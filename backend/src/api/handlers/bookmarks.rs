@@ -0,0 +1,60 @@
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
+use crate::api::errors::{AppError, NoteError};
+use crate::api::handlers::notes::build_response_notes;
+use crate::api::router::RouterState;
+use crate::db::handlers::bookmarks::{add_bookmark, get_bookmarked_notes, remove_bookmark};
+use crate::db::models::User;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use uuid::Uuid;
+
+pub async fn bookmark_note(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(note_id): Path<Uuid>,
+    meta: RequestMeta,
+) -> Result<(StatusCode, Response), AppError> {
+    let bookmark = add_bookmark(&state.db_wrapper, user.id, note_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(err) if err.constraint() == Some("bookmarks_note_id_fkey") => {
+                NoteError::NotFound("Note not found".to_string())
+            }
+            e => NoteError::DatabaseError("Failed to bookmark note".to_string(), e.into()),
+        })?;
+
+    record_event(&state, Some(user.id), AuditAction::NoteBookmark, Some(note_id), &meta, None).await;
+
+    Ok((StatusCode::OK, Json(bookmark).into_response()))
+}
+
+pub async fn unbookmark_note(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(note_id): Path<Uuid>,
+    meta: RequestMeta,
+) -> Result<(StatusCode, Response), AppError> {
+    let removed = remove_bookmark(&state.db_wrapper, user.id, note_id)
+        .await
+        .map_err(|e| NoteError::DatabaseError("Failed to remove bookmark".to_string(), e.into()))?;
+
+    if removed {
+        record_event(&state, Some(user.id), AuditAction::NoteUnbookmark, Some(note_id), &meta, None).await;
+    }
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+/// API handler to list the current user's bookmarked notes.
+pub async fn list_bookmarks(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Response), AppError> {
+    let notes = get_bookmarked_notes(&state.db_wrapper, user.id)
+        .await
+        .map_err(|e| NoteError::DatabaseError("Failed to fetch bookmarks".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(build_response_notes(&state, notes)).into_response()))
+}
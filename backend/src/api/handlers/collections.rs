@@ -0,0 +1,471 @@
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
+use crate::api::errors::{AppError, CollectionError};
+use crate::api::handlers::notes::build_response_notes;
+use crate::api::handlers::votes::VoteRequest;
+use crate::api::models::{
+    CollectionFilter, CreateCollection, ResponseCollection, ResponseCollectionWithNotes,
+    UpdateCollection,
+};
+use crate::api::router::RouterState;
+use crate::db::handlers::collections::{
+    add_note_to_collection, create_collection, delete_collection, get_collection,
+    get_collection_notes, get_collections, remove_note_from_collection, reorder_collection_notes,
+    update_collection, vote_collection,
+};
+use crate::db::models::{DBCollection, User, VoteType};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::{Deserialize, Deserializer};
+use serde_json::json;
+use uuid::Uuid;
+
+const MAX_COLLECTION_NAME_LENGTH: usize = 255;
+/// Most collections returned in a single list
+const MAX_COLLECTIONS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct CreateCollectionRequest {
+    name: String,
+    description: Option<String>,
+    is_public: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct UpdateCollectionRequest {
+    name: Option<String>,
+    /// Left unchanged if missing, and cleared if `null`
+    #[serde(default, deserialize_with = "deserialize_present")]
+    description: Option<Option<String>>,
+    is_public: Option<bool>,
+}
+
+/// Deserializes a field that is present in the request, even if it is `null`, as `Some`, so that a
+/// missing field can be told apart from one being cleared.
+fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+pub struct AddCollectionNoteRequest {
+    note_id: Uuid,
+}
+
+#[derive(Deserialize)]
+pub struct ReorderCollectionRequest {
+    note_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct CollectionQuery {
+    pub num: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct CollectionSearchQuery {
+    query: String,
+}
+
+fn validate_collection_name(name: &str) -> Result<String, CollectionError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(CollectionError::InvalidData(
+            "Collection name is required".to_string(),
+        ));
+    }
+    if name.chars().count() > MAX_COLLECTION_NAME_LENGTH {
+        return Err(CollectionError::InvalidData(format!(
+            "Collection name can be at most {} characters long",
+            MAX_COLLECTION_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Fetches a collection for modification, ensuring the user owns it.
+async fn owned_collection(
+    state: &RouterState,
+    collection_id: Uuid,
+    user: &User,
+) -> Result<DBCollection, CollectionError> {
+    let collection = get_collection(&state.db_wrapper, collection_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to fetch collection".to_string(), e.into())
+        })?
+        .ok_or_else(|| CollectionError::NotFound("Collection not found".to_string()))?;
+
+    if collection.owner_user_id != user.id {
+        return Err(CollectionError::Forbidden(
+            "Only the owner can modify this collection".to_string(),
+        ));
+    }
+
+    Ok(collection)
+}
+
+/// Fetches a single collection visible to the user, along with its notes.
+async fn collection_with_notes(
+    state: &RouterState,
+    filter: CollectionFilter,
+    current_user_id: Option<Uuid>,
+) -> Result<ResponseCollectionWithNotes, CollectionError> {
+    let collection = get_collections(&state.db_wrapper, filter, current_user_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to fetch collection".to_string(), e.into())
+        })?
+        .into_iter()
+        .next()
+        .ok_or_else(|| CollectionError::NotFound("Collection not found".to_string()))?;
+
+    let notes = get_collection_notes(&state.db_wrapper, collection.collection_id, current_user_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to fetch collection notes".to_string(), e.into())
+        })?;
+
+    Ok(ResponseCollectionWithNotes {
+        collection: ResponseCollection::from_collection_with_owner(collection, current_user_id),
+        notes: build_response_notes(state, notes),
+    })
+}
+
+/// Responds with the collections matching the filter.
+async fn respond_with_collections(
+    state: &RouterState,
+    filter: CollectionFilter,
+    current_user_id: Option<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let collections = get_collections(&state.db_wrapper, filter, current_user_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to fetch collections".to_string(), e.into())
+        })?;
+
+    let response_collections: Vec<ResponseCollection> = collections
+        .into_iter()
+        .map(|collection| ResponseCollection::from_collection_with_owner(collection, current_user_id))
+        .collect();
+
+    Ok((StatusCode::OK, Json(response_collections).into_response()))
+}
+
+pub async fn create_new_collection(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Json(payload): Json<CreateCollectionRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    let new_collection = CreateCollection {
+        name: validate_collection_name(&payload.name)?,
+        description: payload.description.filter(|d| !d.trim().is_empty()),
+        is_public: payload.is_public.unwrap_or(false),
+        owner_user_id: user.id,
+    };
+
+    let collection = create_collection(&state.db_wrapper, new_collection)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to create collection".to_string(), e.into())
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionCreate,
+        Some(collection.id),
+        &meta,
+        Some(json!({ "after": &collection })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(collection).into_response()))
+}
+
+/// API handler to list public collections, most upvoted first.
+pub async fn list_collections(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Query(query): Query<CollectionQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let filter = CollectionFilter {
+        limit: Some(query.num.unwrap_or(10).min(MAX_COLLECTIONS)),
+        ..Default::default()
+    };
+    respond_with_collections(&state, filter, user.as_ref().map(|u| u.id)).await
+}
+
+pub async fn search_collections(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Query(query): Query<CollectionSearchQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    if query.query.is_empty() {
+        return Err(CollectionError::InvalidData("Query cannot be empty".to_string()).into());
+    }
+    let filter = CollectionFilter {
+        search_term: Some(query.query),
+        ..Default::default()
+    };
+    respond_with_collections(&state, filter, user.as_ref().map(|u| u.id)).await
+}
+
+/// API handler to list the current user's collections, including private ones.
+pub async fn my_collections(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+) -> Result<(StatusCode, Response), AppError> {
+    let filter = CollectionFilter {
+        owner_user_id: Some(user.id),
+        ..Default::default()
+    };
+    respond_with_collections(&state, filter, Some(user.id)).await
+}
+
+pub async fn collection_by_id(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Path(collection_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let filter = CollectionFilter {
+        collection_id: Some(collection_id),
+        ..Default::default()
+    };
+    let collection = collection_with_notes(&state, filter, user.as_ref().map(|u| u.id)).await?;
+    Ok((StatusCode::OK, Json(collection).into_response()))
+}
+
+/// API handler for share links, which give access to private collections as well.
+pub async fn collection_by_share_token(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Path(share_token): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let filter = CollectionFilter {
+        share_token: Some(share_token),
+        ..Default::default()
+    };
+    let collection = collection_with_notes(&state, filter, user.as_ref().map(|u| u.id)).await?;
+    Ok((StatusCode::OK, Json(collection).into_response()))
+}
+
+pub async fn update_existing_collection(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(collection_id): Path<Uuid>,
+    meta: RequestMeta,
+    Json(payload): Json<UpdateCollectionRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    let before = owned_collection(&state, collection_id, &user).await?;
+
+    let update = UpdateCollection {
+        name: payload.name.as_deref().map(validate_collection_name).transpose()?,
+        description: payload
+            .description
+            .map(|description| description.filter(|d| !d.trim().is_empty())),
+        is_public: payload.is_public,
+    };
+
+    let collection = update_collection(&state.db_wrapper, collection_id, update)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to update collection".to_string(), e.into())
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionUpdate,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "before": before, "after": &collection })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(collection).into_response()))
+}
+
+pub async fn delete_existing_collection(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(collection_id): Path<Uuid>,
+    meta: RequestMeta,
+) -> Result<(StatusCode, Response), AppError> {
+    let before = owned_collection(&state, collection_id, &user).await?;
+
+    delete_collection(&state.db_wrapper, collection_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to delete collection".to_string(), e.into())
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionDelete,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "before": before })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+pub async fn add_collection_note(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(collection_id): Path<Uuid>,
+    meta: RequestMeta,
+    Json(payload): Json<AddCollectionNoteRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    owned_collection(&state, collection_id, &user).await?;
+
+    add_note_to_collection(&state.db_wrapper, collection_id, payload.note_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(err)
+                if err.constraint() == Some("collection_notes_note_id_fkey") =>
+            {
+                CollectionError::NotFound("Note not found".to_string())
+            }
+            e => CollectionError::DatabaseError(
+                "Failed to add note to collection".to_string(),
+                e.into(),
+            ),
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionAddNote,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "note_id": payload.note_id })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+pub async fn remove_collection_note(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path((collection_id, note_id)): Path<(Uuid, Uuid)>,
+    meta: RequestMeta,
+) -> Result<(StatusCode, Response), AppError> {
+    owned_collection(&state, collection_id, &user).await?;
+
+    let removed = remove_note_from_collection(&state.db_wrapper, collection_id, note_id)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError(
+                "Failed to remove note from collection".to_string(),
+                e.into(),
+            )
+        })?;
+
+    if !removed {
+        return Err(CollectionError::NotFound("Note is not in this collection".to_string()).into());
+    }
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionRemoveNote,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "note_id": note_id })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+pub async fn reorder_collection(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(collection_id): Path<Uuid>,
+    meta: RequestMeta,
+    Json(payload): Json<ReorderCollectionRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    owned_collection(&state, collection_id, &user).await?;
+
+    let reordered = reorder_collection_notes(&state.db_wrapper, collection_id, &payload.note_ids)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to reorder collection".to_string(), e.into())
+        })?;
+
+    if !reordered {
+        return Err(CollectionError::InvalidData(
+            "The new order must contain every note in the collection exactly once".to_string(),
+        )
+        .into());
+    }
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionReorder,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "after": payload.note_ids })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
+
+pub async fn add_collection_vote(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(collection_id): Path<Uuid>,
+    meta: RequestMeta,
+    Query(query): Query<VoteRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    let vote_type = VoteType::from_query(&query.vote_type).ok_or_else(|| {
+        CollectionError::BadVote(format!(
+            "Incorrect vote type: {}. Available options are: upvote and remove",
+            query.vote_type
+        ))
+    })?;
+
+    // Only collections the user can see are voteable
+    let filter = CollectionFilter {
+        collection_id: Some(collection_id),
+        ..Default::default()
+    };
+    let visible = get_collections(&state.db_wrapper, filter, Some(user.id))
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to fetch collection".to_string(), e.into())
+        })?;
+    if visible.is_empty() {
+        return Err(CollectionError::NotFound("Collection not found".to_string()).into());
+    }
+
+    let v = vote_collection(&state.db_wrapper, user.id, collection_id, vote_type)
+        .await
+        .map_err(|e| {
+            CollectionError::DatabaseError("Failed to add vote".to_string(), e.into())
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CollectionVote,
+        Some(collection_id),
+        &meta,
+        Some(json!({ "vote_type": query.vote_type, "after": v })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(v).into_response()))
+}
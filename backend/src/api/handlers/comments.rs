@@ -0,0 +1,189 @@
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
+use crate::api::errors::{AppError, CommentError};
+use crate::api::models::{CreateComment, ResponseComment};
+use crate::api::router::RouterState;
+use crate::db::handlers::comments::{
+    create_comment, delete_comment, get_comment, get_note_comments, update_comment_body,
+};
+use crate::db::models::{DBComment, User};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+const MAX_COMMENT_LENGTH: usize = 5000;
+
+#[derive(Deserialize)]
+pub struct CommentRequest {
+    body: String,
+    parent_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+pub struct EditCommentRequest {
+    body: String,
+}
+
+fn validate_comment_body(body: &str) -> Result<String, CommentError> {
+    let body = body.trim();
+    if body.is_empty() {
+        return Err(CommentError::InvalidData("Comment cannot be empty".to_string()));
+    }
+    if body.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(CommentError::InvalidData(format!(
+            "Comments can be at most {} characters long",
+            MAX_COMMENT_LENGTH
+        )));
+    }
+    Ok(body.to_string())
+}
+
+/// Fetches a comment that has not been deleted yet.
+async fn existing_comment(state: &RouterState, comment_id: Uuid) -> Result<DBComment, CommentError> {
+    get_comment(&state.db_wrapper, comment_id)
+        .await
+        .map_err(|e| CommentError::DatabaseError("Failed to fetch comment".to_string(), e.into()))?
+        .filter(|comment| comment.deleted_at.is_none())
+        .ok_or_else(|| CommentError::NotFound("Comment not found".to_string()))
+}
+
+/// API handler to list a note's comments as one-level-deep threads.
+pub async fn list_comments(
+    State(state): State<RouterState>,
+    Path(note_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let comments = get_note_comments(&state.db_wrapper, note_id)
+        .await
+        .map_err(|e| CommentError::DatabaseError("Failed to fetch comments".to_string(), e.into()))?;
+
+    let (top_level, replies): (Vec<_>, Vec<_>) = comments
+        .into_iter()
+        .map(ResponseComment::from_comment_with_author)
+        .partition(|comment| comment.parent_id.is_none());
+
+    let mut threads = top_level;
+    for reply in replies {
+        if let Some(parent) = threads.iter_mut().find(|c| Some(c.id) == reply.parent_id) {
+            parent.replies.push(reply);
+        }
+    }
+
+    Ok((StatusCode::OK, Json(threads).into_response()))
+}
+
+pub async fn add_comment(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(note_id): Path<Uuid>,
+    meta: RequestMeta,
+    Json(payload): Json<CommentRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    let body = validate_comment_body(&payload.body)?;
+
+    // Threads are one level deep, so replies must point at a top-level comment on the same note
+    if let Some(parent_id) = payload.parent_id {
+        let parent = existing_comment(&state, parent_id).await?;
+        if parent.note_id != note_id || parent.parent_comment_id.is_some() {
+            return Err(CommentError::InvalidData(
+                "Replies must be made to a top-level comment on the same note".to_string(),
+            )
+            .into());
+        }
+    }
+
+    let new_comment = CreateComment {
+        note_id,
+        author_user_id: user.id,
+        parent_comment_id: payload.parent_id,
+        body,
+    };
+
+    let comment = create_comment(&state.db_wrapper, new_comment)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(err) if err.constraint() == Some("comments_note_id_fkey") => {
+                CommentError::NotFound("Note not found".to_string())
+            }
+            e => CommentError::DatabaseError("Failed to add comment".to_string(), e.into()),
+        })?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CommentCreate,
+        Some(comment.id),
+        &meta,
+        Some(json!({ "after": &comment })),
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(comment).into_response()))
+}
+
+pub async fn edit_comment(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(comment_id): Path<Uuid>,
+    meta: RequestMeta,
+    Json(payload): Json<EditCommentRequest>,
+) -> Result<(StatusCode, Response), AppError> {
+    let body = validate_comment_body(&payload.body)?;
+
+    let before = existing_comment(&state, comment_id).await?;
+    if before.author_user_id != user.id {
+        return Err(CommentError::Forbidden("Only the author can edit this comment".to_string()).into());
+    }
+
+    let comment = update_comment_body(&state.db_wrapper, comment_id, &body)
+        .await
+        .map_err(|e| CommentError::DatabaseError("Failed to edit comment".to_string(), e.into()))?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::CommentUpdate,
+        Some(comment_id),
+        &meta,
+        Some(json!({ "before": before.body, "after": &comment.body })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json(comment).into_response()))
+}
+
+/// API handler to delete a comment. Authors delete their own comments, admins can remove anyone's.
+pub async fn remove_comment(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(comment_id): Path<Uuid>,
+    meta: RequestMeta,
+) -> Result<(StatusCode, Response), AppError> {
+    let before = existing_comment(&state, comment_id).await?;
+
+    let (removed_by, action) = if before.author_user_id == user.id {
+        (None, AuditAction::CommentDelete)
+    } else if user.is_admin {
+        (Some(user.id), AuditAction::CommentRemove)
+    } else {
+        return Err(CommentError::Forbidden("Only the author can delete this comment".to_string()).into());
+    };
+
+    delete_comment(&state.db_wrapper, comment_id, removed_by)
+        .await
+        .map_err(|e| CommentError::DatabaseError("Failed to delete comment".to_string(), e.into()))?;
+
+    record_event(
+        &state,
+        Some(user.id),
+        action,
+        Some(comment_id),
+        &meta,
+        Some(json!({ "before": before })),
+    )
+    .await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
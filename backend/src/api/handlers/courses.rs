@@ -0,0 +1,62 @@
+use crate::api::errors::{AppError, CourseError};
+use crate::api::handlers::notes::build_response_notes;
+use crate::api::router::RouterState;
+use crate::db::handlers::courses::{get_course_by_code, get_course_notes, get_courses};
+use crate::db::models::{normalize_course_code, User};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct CourseQuery {
+    pub query: Option<String>,
+    pub department: Option<String>,
+    pub num: Option<usize>,
+}
+
+/// API handler to list courses, optionally filtered for autocompletion. Course codes that notes use
+/// but that aren't in the catalog are listed too, without an id.
+pub async fn list_courses(
+    State(state): State<RouterState>,
+    Query(query): Query<CourseQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let search = query
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+
+    let courses = get_courses(
+        &state.db_wrapper,
+        search,
+        query.department.as_deref(),
+        query.num.unwrap_or(50),
+    )
+    .await
+    .map_err(|e| CourseError::DatabaseError("Failed to fetch courses".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(courses).into_response()))
+}
+
+/// API handler to list the notes of a course, whether or not it's in the catalog.
+pub async fn course_notes(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Path(code): Path<String>,
+) -> Result<(StatusCode, Response), AppError> {
+    let course_code = normalize_course_code(&code);
+    let course = get_course_by_code(&state.db_wrapper, &course_code)
+        .await
+        .map_err(|e| CourseError::DatabaseError("Failed to fetch course".to_string(), e.into()))?;
+
+    let notes = get_course_notes(&state.db_wrapper, &course_code, user.as_ref().map(|u| u.id))
+        .await
+        .map_err(|e| CourseError::DatabaseError("Failed to fetch notes".to_string(), e.into()))?;
+    if course.is_none() && notes.is_empty() {
+        return Err(CourseError::NotFound(format!("Course {} not found", code)))?;
+    }
+
+    Ok((StatusCode::OK, Json(build_response_notes(&state, notes)).into_response()))
+}
@@ -0,0 +1,55 @@
+use crate::api::errors::{AppError, FileError};
+use crate::api::router::RouterState;
+use crate::api::signed_urls::verify_file_signature;
+use crate::storage::{Storage, StorageBackend};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use chrono::Utc;
+use serde::Deserialize;
+use std::time::Duration;
+use tower_http::services::ServeFile;
+
+/// How long the presigned URLs that downloads from S3 are redirected to stay valid
+const REDIRECT_URL_EXPIRY: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Deserialize)]
+pub struct SignedFileQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// API handler to download a stored file through a signed URL.
+///
+/// Files on the local disk are served directly, with support for range requests. Files in S3
+/// are redirected to a short-lived presigned URL instead of being proxied.
+pub async fn download_file(
+    State(state): State<RouterState>,
+    Path(key): Path<String>,
+    Query(query): Query<SignedFileQuery>,
+    request: Request,
+) -> Result<(StatusCode, Response), AppError> {
+    if !verify_file_signature(&state.env_vars.signing_secret, &key, query.expires, &query.signature) {
+        return Err(FileError::Forbidden("Invalid file link".to_string()))?;
+    }
+    if query.expires < Utc::now().timestamp() {
+        return Err(FileError::Expired("File link has expired, please reload the page".to_string()))?;
+    }
+
+    match &state.env_vars.storage {
+        StorageBackend::Local(storage) => {
+            let response = ServeFile::new(storage.path(&key))
+                .try_call(request)
+                .await
+                .map_err(|err| FileError::StorageError("Failed to read file".to_string(), err.into()))?;
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(FileError::NotFound("File not found".to_string()))?;
+            }
+            Ok((response.status(), response.into_response()))
+        }
+        StorageBackend::S3(storage) => Ok((
+            StatusCode::TEMPORARY_REDIRECT,
+            Redirect::temporary(&storage.presign(&key, REDIRECT_URL_EXPIRY)).into_response(),
+        )),
+    }
+}
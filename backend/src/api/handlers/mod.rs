@@ -1,5 +1,14 @@
 // backend/src/api/handlers/mod.rs
+pub mod admin;
+pub mod bookmarks;
+pub mod collections;
+pub mod comments;
+pub mod courses;
+pub mod files;
 pub mod misc;
 pub mod notes;
+pub mod professors;
+pub mod tags;
+pub mod uploads;
 pub mod votes;
 pub mod auth;
\ No newline at end of file
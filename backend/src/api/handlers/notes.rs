@@ -1,20 +1,93 @@
+use crate::alerts::alert_moderators;
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
 use crate::api::errors::{AppError, NoteError};
-use crate::api::models::{CreateNote, ResponseNote, ResponseUser};
+use crate::api::handlers::tags::parse_tags;
+use crate::api::models::{
+    CreateNote, NoteFilter, NoteUploadFields, ResponseNote, ResponsePreview, ResponseRelatedNotes,
+    ResponseSearch, ResponseUser,
+};
 use crate::api::router::RouterState;
+use crate::api::signed_urls::signed_file_url;
+use crate::convert::{convert_to_pdf, ConvertError, MAX_IMAGES};
+use crate::pdf::{inspect_pdf, PdfError};
+use crate::scan::{ScanResult, Scanner};
+use crate::storage::Storage;
+use crate::preview::{page_preview_file_name, preview_file_name, PreviewFormat, PreviewSize};
+use crate::upload::{stream_to_temp_file, TempUpload, UploadError};
+use crate::db::handlers::courses::get_course_by_code;
+use crate::db::handlers::duplicates::get_note_id_by_file_hash;
+use crate::db::handlers::jobs::enqueue_job;
 use crate::db::handlers::notes::{
-    create_note, get_note_by_id, get_notes, increment_note_downloads, search_notes_by_query,
-    update_note_preview_status,
+    create_note, fuzzy_search_notes, get_note_by_id, get_notes, get_notes_by_ids,
+    get_related_note_ids, increment_note_downloads, search_notes_by_query,
+};
+use crate::db::handlers::professors::link_note_professors;
+use crate::db::handlers::quarantine::insert_quarantined_file;
+use crate::db::handlers::suggestions::{get_search_correction, get_suggestions};
+use crate::db::handlers::tags::resolve_tags;
+use crate::db::models::{
+    is_valid_course_code, normalize_course_code, ContentType, JobKind, NoteWithUser, Semester, User,
+    MAX_COURSE_CODE_LENGTH,
 };
-use crate::db::models::User;
-use axum::body::Bytes;
 use axum::extract::{multipart::Multipart, Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::{Extension, Json};
-use chrono::Utc;
+use chrono::{Datelike, Utc};
+use std::time::Duration;
 use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// How long preview URLs in responses stay valid, if the storage backend signs them
+const PREVIEW_URL_EXPIRY: Duration = Duration::from_secs(60 * 60);
+
+/// Signed URL a note's PDF can be downloaded from, so that links to notes expire.
+pub(crate) fn note_file_url(state: &RouterState, note_id: Uuid) -> String {
+    let key = state.env_vars.paths.get_note_slug(&format!("{}.pdf", note_id));
+    signed_file_url(
+        &state.env_vars,
+        &key,
+        Duration::from_secs(state.env_vars.file_url_expiration_seconds),
+    )
+}
+
+fn preview_url(state: &RouterState, file_name: &str) -> String {
+    let key = state.env_vars.paths.get_preview_slug(file_name);
+    state.env_vars.storage.presign(&key, PREVIEW_URL_EXPIRY)
+}
+
+/// Builds the API response for a note, filling in its file and preview URLs.
+pub(crate) fn build_response_note(state: &RouterState, note: NoteWithUser) -> ResponseNote {
+    let file_url = note_file_url(state, note.note_id);
+    let preview_image_url = preview_url(state, &preview_file_name(note.note_id));
+    let note_id = note.note_id;
+    let page_preview_url =
+        |page, size, format| preview_url(state, &page_preview_file_name(note_id, page, size, format));
+    let previews = (1..=note.note_preview_pages)
+        .flat_map(|page| PreviewSize::ALL.into_iter().map(move |size| (page, size)))
+        .map(|(page, size)| ResponsePreview {
+            page,
+            size,
+            url: page_preview_url(page, size, PreviewFormat::Jpeg),
+            variants: PreviewFormat::ALL
+                .into_iter()
+                .filter(|format| *format != PreviewFormat::Jpeg)
+                .map(|format| (format, page_preview_url(page, size, format)))
+                .collect(),
+        })
+        .collect();
+    ResponseNote::from_note_with_user(note, file_url, preview_image_url, previews)
+}
+
+pub(crate) fn build_response_notes(state: &RouterState, notes: Vec<NoteWithUser>) -> Vec<ResponseNote> {
+    notes
+        .into_iter()
+        .map(|note| build_response_note(state, note))
+        .collect()
+}
+
 #[derive(Deserialize)]
 pub struct NoteQuery {
     pub num: Option<usize>,
@@ -25,32 +98,18 @@ pub async fn list_notes(
     State(state): State<RouterState>,
     Extension(user): Extension<Option<User>>,
     Query(query): Query<NoteQuery>,
+    Query(filter): Query<NoteFilter>,
 ) -> Result<(StatusCode, Response), AppError> {
     match get_notes(
         &state.db_wrapper,
         query.num.unwrap_or(10),
+        &filter,
         user.as_ref().map(|u| u.id),
     )
         .await
     {
         Ok(notes) => {
-            let response_notes: Vec<ResponseNote> = notes
-                .into_iter()
-                .map(|note| {
-                    let file_url = state
-                        .env_vars
-                        .paths
-                        .get_note_url(&format!("{}.pdf", note.note_id))
-                        .unwrap();
-                    let preview_image_url =
-                        state
-                            .env_vars
-                            .paths
-                            .get_preview_url(&format!("{}.jpg", note.note_id))
-                            .unwrap();
-                    ResponseNote::from_note_with_user(note, file_url, preview_image_url)
-                })
-                .collect();
+            let response_notes = build_response_notes(&state, notes);
             Ok((StatusCode::OK, Json(response_notes).into_response()))
         }
         Err(err) => {
@@ -67,21 +126,7 @@ pub async fn note_by_id(
     tracing::debug!("Fetching note with ID: {}", note_id);
     match get_note_by_id(&state.db_wrapper, note_id, user.as_ref().map(|u| u.id)).await {
         Ok(note) => {
-            let file_url = state
-                .env_vars
-                .paths
-                .get_note_url(&format!("{}.pdf", note.note_id))
-                .unwrap();
-            let preview_image_url =
-
-                state
-                    .env_vars
-                    .paths
-                    .get_preview_url(&format!("{}.jpg", note.note_id))
-                    .unwrap();
-
-            let response_note =
-                ResponseNote::from_note_with_user(note, file_url, preview_image_url);
+            let response_note = build_response_note(&state, note);
             Ok((StatusCode::OK, Json(response_note).into_response()))
         }
         Err(err) => {
@@ -91,93 +136,170 @@ pub async fn note_by_id(
     }
 }
 
+const RELATED_NOTES_PER_KIND: usize = 6;
+
+/// API handler to recommend notes related to a note.
+pub async fn related_notes(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Path(note_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    // Only the note ids are cached, the notes themselves are fetched per user
+    let related = match state.related_notes_cache.get(&note_id) {
+        Some(related) => related,
+        None => {
+            let related = get_related_note_ids(&state.db_wrapper, note_id, RELATED_NOTES_PER_KIND)
+                .await
+                .map_err(|err| {
+                    NoteError::DatabaseError("Failed to fetch related notes".to_string(), err.into())
+                })?
+                .ok_or_else(|| NoteError::NotFound("Note not found".to_string()))?;
+            state.related_notes_cache.insert(note_id, related.clone());
+            related
+        }
+    };
+
+    let all_ids: Vec<Uuid> = related
+        .same_course
+        .iter()
+        .chain(&related.same_professor)
+        .chain(&related.also_liked)
+        .copied()
+        .collect();
+    let notes = get_notes_by_ids(&state.db_wrapper, &all_ids, user.as_ref().map(|u| u.id))
+        .await
+        .map_err(|err| NoteError::DatabaseError("Failed to fetch related notes".to_string(), err.into()))?;
+    let mut notes_by_id: HashMap<Uuid, NoteWithUser> =
+        notes.into_iter().map(|note| (note.note_id, note)).collect();
+
+    // A note that fits several lists is only shown in the first one
+    let mut take = |ids: &[Uuid]| -> Vec<ResponseNote> {
+        ids.iter()
+            .filter_map(|id| notes_by_id.remove(id))
+            .map(|note| build_response_note(&state, note))
+            .collect()
+    };
+    let response = ResponseRelatedNotes {
+        same_course: take(&related.same_course),
+        same_professor: take(&related.same_professor),
+        also_liked: take(&related.also_liked),
+    };
+
+    Ok((StatusCode::OK, Json(response).into_response()))
+}
+
 #[derive(Deserialize)]
 pub struct SearchQuery {
     query: String,
 }
 
+/// Below this many exact matches, search falls back to typo-tolerant matching.
+const FUZZY_SEARCH_MIN_RESULTS: usize = 3;
+const FUZZY_SEARCH_MAX_RESULTS: usize = 50;
+
 pub async fn search_notes(
     State(state): State<RouterState>,
     Extension(user): Extension<Option<User>>,
     Query(query): Query<SearchQuery>,
+    Query(filter): Query<NoteFilter>,
 ) -> Result<(StatusCode, Response), AppError> {
     tracing::debug!("Search query: {:?}", query.query);
-    if query.query.is_empty() {
+    let search = query.query.trim();
+    if search.is_empty() {
         return Err(NoteError::InvalidData("Query cannot be empty".to_string()).into());
     }
-    match search_notes_by_query(&state.db_wrapper, &query.query, user.as_ref().map(|u| u.id)).await
-    {
-        Ok(notes) => {
-            let response_notes: Vec<ResponseNote> = notes
-                .into_iter()
-                .map(|note| {
-                    let file_url = state
-                        .env_vars
-                        .paths
-                        .get_note_url(&format!("{}.pdf", note.note_id))
-                        .unwrap();
-                    let preview_image_url =
-
-                        state
-                            .env_vars
-                            .paths
-                            .get_preview_url(&format!("{}.jpg", note.note_id))
-                            .unwrap();
-
-
-                    ResponseNote::from_note_with_user(note, file_url, preview_image_url)
-                })
-                .collect();
-            Ok((StatusCode::OK, Json(response_notes).into_response()))
+    let user_id = user.as_ref().map(|u| u.id);
+
+    let mut notes = search_notes_by_query(&state.db_wrapper, search, &filter, user_id)
+        .await
+        .map_err(|err| NoteError::DatabaseError("Failed to fetch notes".to_string(), err.into()))?;
+
+    let mut fuzzy = false;
+    let mut did_you_mean = None;
+    let exact_matches = notes.len();
+    if exact_matches < FUZZY_SEARCH_MIN_RESULTS {
+        let fuzzy_notes =
+            fuzzy_search_notes(&state.db_wrapper, search, &filter, FUZZY_SEARCH_MAX_RESULTS, user_id)
+                .await
+                .map_err(|err| {
+                    NoteError::DatabaseError("Failed to fetch notes".to_string(), err.into())
+                })?;
+        for note in fuzzy_notes {
+            if !notes.iter().any(|n| n.note_id == note.note_id) {
+                notes.push(note);
+                fuzzy = true;
+            }
         }
-        Err(err) => {
-            Err(NoteError::DatabaseError("Failed to fetch notes".to_string(), err.into()).into())
+
+        // Only suggest a correction when the query as typed matched nothing
+        if exact_matches == 0 {
+            did_you_mean = get_search_correction(&state.db_wrapper, search)
+                .await
+                .map_err(|err| {
+                    NoteError::DatabaseError("Failed to fetch search suggestion".to_string(), err.into())
+                })?;
         }
     }
+
+    let response = ResponseSearch {
+        notes: build_response_notes(&state, notes),
+        fuzzy,
+        did_you_mean,
+    };
+    Ok((StatusCode::OK, Json(response).into_response()))
+}
+
+const MAX_SUGGESTIONS: usize = 50;
+
+#[derive(Deserialize)]
+pub struct SuggestQuery {
+    q: String,
+    num: Option<usize>,
 }
 
-use std::process::Command;
-
-async fn generate_preview_image(
-    pdf_path: &str,
-    preview_path: &str,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let output = Command::new("pdftoppm")
-        .args(&[
-            "-singlefile",
-            "-jpeg",
-            "-scale-to-x",
-            "800",
-            "-scale-to-y",
-            "-1",
-            pdf_path,
-            preview_path.trim_end_matches(".jpg"),
-        ])
-        .output()?;
-
-    if !output.status.success() {
-        return Err(format!(
-            "pdftoppm failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
-            .into());
+/// API handler to autocomplete the search box with matching course codes, course names, professors and tags.
+pub async fn suggest(
+    State(state): State<RouterState>,
+    Query(query): Query<SuggestQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let search = query.q.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if search.is_empty() {
+        return Err(NoteError::InvalidData("Query cannot be empty".to_string()).into());
+    }
+    let num = query.num.unwrap_or(10).min(MAX_SUGGESTIONS);
+
+    let cache_key = (search, num);
+    if let Some(suggestions) = state.suggestion_cache.get(&cache_key) {
+        return Ok((StatusCode::OK, Json(suggestions).into_response()));
     }
 
-    Ok(())
+    let course_code_prefix = normalize_course_code(&cache_key.0);
+    let suggestions = get_suggestions(
+        &state.db_wrapper,
+        &cache_key.0,
+        Some(course_code_prefix.as_str()).filter(|prefix| !prefix.is_empty()),
+        num,
+    )
+    .await
+    .map_err(|err| NoteError::DatabaseError("Failed to fetch suggestions".to_string(), err.into()))?;
+
+    state.suggestion_cache.insert(cache_key, suggestions.clone());
+    Ok((StatusCode::OK, Json(suggestions).into_response()))
+}
+
+fn duplicate_note_error(state: &RouterState, existing_note_id: Uuid) -> NoteError {
+    NoteError::Duplicate(existing_note_id, note_file_url(state, existing_note_id))
 }
 
 // Integration into your upload_note function
 pub async fn upload_note(
     State(state): State<RouterState>,
     Extension(user): Extension<User>,
+    meta: RequestMeta,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, Response), AppError> {
-    let mut course_name = String::new();
-    let mut course_code = String::new();
-    let mut description: Option<String> = None;
-    let mut professor_names: Option<Vec<String>> = None;
-    let mut tags: Vec<String> = Vec::new();
-    let mut file_data: Option<Bytes> = None;
+    let mut fields = NoteUploadFields::default();
+    let mut uploaded_files: Vec<TempUpload> = Vec::new();
     let file_size_limit = state.env_vars.file_size_limit << 20;
     tracing::info!("Upload request received, file size limit: {} MiB", file_size_limit >> 20);
 
@@ -188,33 +310,36 @@ pub async fn upload_note(
             None => continue,
         };
 
+        // Several files can be uploaded as images merged into a single note
         if name == "file" {
-            // Validate content type - only accept PDFs
-            if let Some(content_type) = field.content_type() {
-                if content_type != "application/pdf" {
-                    return Err(NoteError::InvalidData(
-                        "Only PDF files are supported".to_string(),
-                    ))?;
-                }
-            } else {
-                return Err(NoteError::InvalidData(
-                    "Content-type header not found. File type could not be determined".to_string(),
-                ))?;
-            }
-
-            let data = field
-                .bytes()
-                .await
-                .map_err(|_| NoteError::UploadFailed("Failed to read file bytes".to_string()))?;
-
-            if data.len() > file_size_limit {
+            if uploaded_files.len() >= MAX_IMAGES {
                 return Err(NoteError::InvalidData(format!(
-                    "File size too big. Only files up to {} MiB are allowed.",
-                    file_size_limit >> 20
+                    "At most {} images can be uploaded at once",
+                    MAX_IMAGES
                 )))?;
             }
 
-            file_data = Some(data);
+            // The content type is set by the client, so the file itself is checked once it's saved
+            // Stream the file to disk instead of buffering it, so large uploads don't use much memory
+            let uploaded_size: usize = uploaded_files.iter().map(|file| file.size).sum();
+            let upload = stream_to_temp_file(
+                field,
+                state.env_vars.paths.get_notes_dir(),
+                file_size_limit - uploaded_size,
+            )
+            .await
+                .map_err(|err| match err {
+                    UploadError::TooLarge => NoteError::InvalidData(format!(
+                        "File size too big. Only files up to {} MiB are allowed.",
+                        file_size_limit >> 20
+                    )),
+                    UploadError::Read(_) => NoteError::UploadFailed("Failed to read file bytes".to_string()),
+                    UploadError::Io(err) => {
+                        tracing::error!("Failed to write uploaded file: {}", err);
+                        NoteError::UploadFailed("Failed to save file".to_string())
+                    }
+                })?;
+            uploaded_files.push(upload);
             continue;
         }
 
@@ -225,91 +350,296 @@ pub async fn upload_note(
             .map_err(|_| NoteError::UploadFailed(format!("Invalid format for field: {}", name)))?;
 
         match name.as_str() {
-            "course_name" => course_name = data,
-            "course_code" => course_code = data,
-            "description" => {
-                if !data.trim().is_empty() {
-                    description = Some(data);
-                }
+            "course_name" => fields.course_name = data,
+            "course_code" => fields.course_code = data,
+            "description" => fields.description = Some(data),
+            "professor_names" => fields.professor_names = data.split(',').map(str::to_string).collect(),
+            "tags" => fields.tags = data.split(',').map(str::to_string).collect(),
+            "academic_year" if !data.trim().is_empty() => {
+                let year = data.trim().parse::<i32>().map_err(|_| {
+                    NoteError::InvalidData(format!("Invalid academic year: {}", data.trim()))
+                })?;
+                fields.academic_year = Some(year);
             }
-            "professor_names" => {
-                let names: Vec<String> = data
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
-                if !names.is_empty() {
-                    professor_names = Some(names);
-                }
+            "semester" if !data.trim().is_empty() => {
+                fields.semester = Some(data.parse::<Semester>().map_err(NoteError::InvalidData)?);
             }
-            "tags" => {
-                tags = data
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
+            "content_type" if !data.trim().is_empty() => {
+                fields.content_type = Some(data.parse::<ContentType>().map_err(NoteError::InvalidData)?);
             }
             _ => (),
         }
     }
 
-    // Validate required fields
-    if course_name.trim().is_empty() {
-        return Err(NoteError::InvalidData(
-            "Course name is required".to_string(),
-        ))?;
+    if uploaded_files.is_empty() {
+        return Err(NoteError::InvalidData("File not provided".to_string()))?;
+    }
+    process_upload(&state, &user, &meta, fields, uploaded_files).await
+}
+
+/// Turns uploaded files into a note: scans them for malware, converts them to a PDF and saves it.
+pub(crate) async fn process_upload(
+    state: &RouterState,
+    user: &User,
+    meta: &RequestMeta,
+    fields: NoteUploadFields,
+    files: Vec<TempUpload>,
+) -> Result<(StatusCode, Response), AppError> {
+    let files = scan_uploads(state, user, &fields.course_code, files).await?;
+    let uploaded_file = convert_upload(state, files).await?;
+    let new_note = prepare_note(state, fields, user.id, uploaded_file.sha256.clone()).await?;
+
+    save_uploaded_note(state, user, meta, new_note, uploaded_file).await
+}
+
+/// Scans uploaded files for malware before anything else opens them, since converting office
+/// documents and images runs them through external tools. Infected files are quarantined.
+async fn scan_uploads(
+    state: &RouterState,
+    user: &User,
+    course_code: &str,
+    files: Vec<TempUpload>,
+) -> Result<Vec<TempUpload>, NoteError> {
+    let mut clean_files = Vec::with_capacity(files.len());
+    for file in files {
+        match state.env_vars.scanner.scan(file.path()).await {
+            Ok(ScanResult::Clean) => clean_files.push(file),
+            Ok(ScanResult::Infected(signature)) => {
+                quarantine_upload(state, user, course_code, file, &signature).await;
+                return Err(NoteError::Infected(
+                    "Malware was found in this file, so it can't be uploaded".to_string(),
+                ));
+            }
+            Err(err) => {
+                tracing::error!("Failed to scan upload for malware: {}", err);
+                return Err(NoteError::UploadFailed("Failed to scan file for malware".to_string()));
+            }
+        }
     }
-    if course_code.trim().is_empty() {
+    Ok(clean_files)
+}
+
+/// Converts uploaded images or office documents into the single PDF stored for a note.
+async fn convert_upload(state: &RouterState, files: Vec<TempUpload>) -> Result<TempUpload, NoteError> {
+    convert_to_pdf(files, state.env_vars.paths.get_notes_dir())
+        .await
+        .map_err(|err| match err {
+            ConvertError::Unsupported(msg) => NoteError::InvalidData(msg),
+            ConvertError::Busy => NoteError::Unavailable("The server is busy, please try again later".to_string()),
+            ConvertError::Failed(msg) => {
+                tracing::info!("Failed to convert upload to PDF: {}", msg);
+                NoteError::InvalidData("The file could not be converted to PDF".to_string())
+            }
+            err => {
+                tracing::error!("{}", err);
+                NoteError::UploadFailed("Failed to convert file to PDF".to_string())
+            }
+        })
+}
+
+/// Validates and normalizes the details of an uploaded note.
+pub(crate) async fn prepare_note(
+    state: &RouterState,
+    fields: NoteUploadFields,
+    uploader_user_id: Uuid,
+    file_sha256: String,
+) -> Result<CreateNote, NoteError> {
+    // Validate required fields
+    if fields.course_code.trim().is_empty() {
         return Err(NoteError::InvalidData(
             "Course code is required".to_string(),
-        ))?;
+        ));
+    }
+    let course_code = normalize_course_code(&fields.course_code);
+    if !is_valid_course_code(&course_code) {
+        return Err(NoteError::InvalidData(format!(
+            "Invalid course code: {}. Course codes are up to {} letters and digits, like MA10001",
+            fields.course_code.trim(),
+            MAX_COURSE_CODE_LENGTH
+        )));
+    }
+
+    // Courses in the catalog always use their canonical name, so the name is optional for them.
+    // Other courses keep the normalized code as free text.
+    let course = get_course_by_code(&state.db_wrapper, &course_code)
+        .await
+        .map_err(|err| NoteError::DatabaseError("Failed to fetch course".to_string(), err.into()))?;
+    let course_name = match &course {
+        Some(course) => course.name.clone(),
+        None if fields.course_name.trim().is_empty() => {
+            return Err(NoteError::InvalidData(
+                "Course name is required".to_string(),
+            ));
+        }
+        None => fields.course_name.trim().to_string(),
+    };
+
+    // Merge synonyms so the same topic doesn't end up under several tags
+    let tags = resolve_tags(&state.db_wrapper, &parse_tags(fields.tags.iter().map(String::as_str)))
+        .await
+        .map_err(|err| NoteError::DatabaseError("Failed to resolve tags".to_string(), err.into()))?;
+
+    if let Some(year) = fields.academic_year {
+        if !(1951..=Utc::now().year() + 1).contains(&year) {
+            return Err(NoteError::InvalidData(format!("Invalid academic year: {}", year)));
+        }
     }
 
-    let file_bytes = file_data.ok_or(NoteError::InvalidData("File not provided".to_string()))?;
+    let professor_names: Vec<String> = fields
+        .professor_names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect();
 
-    let new_note = CreateNote {
+    Ok(CreateNote {
         course_name,
         course_code,
-        description,
-        professor_names,
+        course_id: course.map(|course| course.id),
+        description: fields.description.filter(|d| !d.trim().is_empty()),
+        professor_names: Some(professor_names).filter(|names| !names.is_empty()),
         tags,
+        academic_year: fields.academic_year,
+        semester: fields.semester,
+        content_type: fields.content_type,
+        file_sha256,
+        page_count: None,
+        pdf_metadata: None,
         has_preview_image: false,
-        uploader_user_id: user.id,
+        uploader_user_id,
         timestamp: Utc::now(),
-    };
+    })
+}
 
-    let (mut tx, note) = create_note(&state.db_wrapper, new_note)
+/// Moves an upload that malware was found in to the quarantine directory, records it for
+/// moderators to review and alerts them.
+async fn quarantine_upload(
+    state: &RouterState,
+    user: &User,
+    course_code: &str,
+    uploaded_file: TempUpload,
+    signature: &str,
+) {
+    let id = Uuid::new_v4();
+    let course_code = normalize_course_code(course_code);
+    let file_sha256 = uploaded_file.sha256.clone();
+    // Files are scanned as uploaded, before conversion, so the file type isn't known
+    let paths = &state.env_vars.paths;
+    let file_key = paths.get_quarantine_slug(&paths.get_note_slug(&id.to_string()));
+    if let Err(err) = uploaded_file.persist(&state.env_vars.storage, &file_key).await {
+        tracing::error!("Failed to quarantine infected upload {}, it was deleted instead: {}", id, err);
+    }
+
+    if let Err(err) = insert_quarantined_file(
+        &state.db_wrapper,
+        id,
+        &file_key,
+        &file_sha256,
+        signature,
+        user.id,
+        &course_code,
+    )
+    .await
+    {
+        tracing::error!("Failed to record quarantined upload {}: {}", id, err);
+    }
+
+    alert_moderators(
+        &state.env_vars,
+        format!(
+            "Malware ({}) found in an upload for {} by {} <{}>. The file was quarantined as {}.",
+            signature, course_code, user.full_name, user.email, file_key
+        ),
+    );
+}
+
+/// Creates a note for an uploaded file and moves the file into place.
+async fn save_uploaded_note(
+    state: &RouterState,
+    user: &User,
+    meta: &RequestMeta,
+    mut new_note: CreateNote,
+    uploaded_file: TempUpload,
+) -> Result<(StatusCode, Response), AppError> {
+    let pdf_info = inspect_pdf(uploaded_file.path().to_path_buf())
         .await
-        .map_err(|err| NoteError::DatabaseError("Failed to create note".to_string(), err.into()))?;
+        .map_err(|err| match err {
+            PdfError::NotPdf => NoteError::InvalidData("Only PDF files are supported".to_string()),
+            PdfError::Encrypted => NoteError::InvalidData(
+                "Encrypted or password-protected PDFs are not supported".to_string(),
+            ),
+            PdfError::Invalid(msg) => {
+                tracing::info!("Rejected invalid PDF: {}", msg);
+                NoteError::InvalidData("The PDF is damaged and could not be opened".to_string())
+            }
+            PdfError::TimedOut => NoteError::InvalidData("The PDF took too long to open".to_string()),
+            PdfError::Busy => NoteError::Unavailable("The server is busy, please try again later".to_string()),
+            err => {
+                tracing::error!("Failed to validate uploaded PDF: {}", err);
+                NoteError::UploadFailed("Failed to validate file".to_string())
+            }
+        })?;
+    new_note.page_count = Some(pdf_info.page_count);
+    new_note.pdf_metadata = serde_json::to_value(&pdf_info.metadata).ok();
 
+    let file_sha256 = new_note.file_sha256.clone();
+    if let Some(existing_note_id) = get_note_id_by_file_hash(&state.db_wrapper, &file_sha256)
+        .await
+        .map_err(|err| NoteError::DatabaseError("Failed to check for duplicates".to_string(), err.into()))?
+    {
+        return Err(duplicate_note_error(state, existing_note_id).into());
+    }
 
-    let file_url = state
-        .env_vars
-        .paths
-        .get_note_url(&format!("{}.pdf", note.id))
-        .unwrap();
+    let (mut tx, mut note) = match create_note(&state.db_wrapper, new_note).await {
+        Ok(created) => created,
+        // Lost a race with a concurrent upload of the same file
+        Err(sqlx::Error::Database(err)) if err.constraint() == Some("notes_file_sha256_idx") => {
+            let existing_note_id = get_note_id_by_file_hash(&state.db_wrapper, &file_sha256)
+                .await
+                .map_err(|err| NoteError::DatabaseError("Failed to check for duplicates".to_string(), err.into()))?
+                .ok_or_else(|| NoteError::UploadFailed("Failed to create note".to_string()))?;
+            return Err(duplicate_note_error(state, existing_note_id).into());
+        }
+        Err(err) => {
+            return Err(NoteError::DatabaseError("Failed to create note".to_string(), err.into()).into())
+        }
+    };
+
+    // Names are linked to the professor they exactly match, or to a new professor
+    if let Some(names) = &note.professor_names {
+        let canonical_names = link_note_professors(&mut tx, note.id, names)
+            .await
+            .map_err(|err| NoteError::DatabaseError("Failed to link professors".to_string(), err.into()))?;
+        note.professor_names = Some(canonical_names);
+    }
+
+    let audit_diff = json!({ "after": &note });
 
-    let preview_image_url = state
-        .env_vars
-        .paths
-        .get_preview_url(&format!("{}.jpg", note.id))
-        .unwrap();
+    let file_url = note_file_url(state, note.id);
+    let preview_image_url = preview_url(state, &preview_file_name(note.id));
 
-    let mut note_with_user = ResponseNote {
+    let note_with_user = ResponseNote {
         id: note.id,
         course_name: note.course_name,
         course_code: note.course_code,
         description: note.description,
         professor_names: note.professor_names,
         tags: note.tags,
+        academic_year: note.academic_year,
+        semester: note.semester,
+        content_type: note.content_type,
+        page_count: note.page_count,
         is_public: note.is_public,
         has_preview_image: false,
         preview_image_url: preview_image_url.clone(),
+        previews: Vec::new(),
         file_url,
         upvotes: 0,
         downvotes: 0,
+        comment_count: 0,
         downloads: 0,
         user_vote: None,
+        bookmarked: false,
         uploader_user: ResponseUser {
             id: user.id,
             google_id: user.google_id.clone(),
@@ -321,40 +651,28 @@ pub async fn upload_note(
         created_at: note.created_at,
     };
 
-    let file_path = state.env_vars.paths.get_note_path(&format!("{}.pdf", note.id));
-    if let Some(parent_dir) = file_path.parent() {
-        if !parent_dir.exists() {
-            tokio::fs::create_dir_all(parent_dir).await.map_err(|_| {
-                NoteError::UploadFailed("Failed to create upload directory".to_string())
-            })?;
-        }
-    }
-
-    let preview_path = state.env_vars.paths.get_preview_path(&format!("{}.jpg", note.id));
-    if let Some(parent_dir) = preview_path.parent() {
-        if !parent_dir.exists() {
-            tokio::fs::create_dir_all(parent_dir).await.map_err(|_| {
-                NoteError::UploadFailed("Failed to create preview directory".to_string())
-            })?;
-        }
-    }
-
-    // Write pdf file data
-    if tokio::fs::write(&file_path, &file_bytes).await.is_ok() {
-        // Try to build preview
-        let result = generate_preview_image(
-            file_path.to_str().unwrap(),
-            preview_path.to_str().unwrap(),
-        ).await;
-        if result.is_ok() {
-            let _res = update_note_preview_status(&mut tx, note.id, true).await;
-            note_with_user.has_preview_image = true;
+    // Move the uploaded pdf into storage
+    let file_key = state.env_vars.paths.get_note_slug(&format!("{}.pdf", note.id));
+    if uploaded_file.persist(&state.env_vars.storage, &file_key).await.is_ok() {
+        // The preview and near duplicate check are left to the job workers, so the upload returns right away
+        for kind in [JobKind::RenderPreview, JobKind::ExtractText] {
+            if let Err(err) = enqueue_job(&mut tx, note.id, kind).await {
+                tracing::error!("Failed to queue {:?} job for note {}: {}", kind, note.id, err);
+            }
         }
         if tx.commit().await.is_ok() {
+            record_event(
+                state,
+                Some(user.id),
+                AuditAction::NoteUpload,
+                Some(note_with_user.id),
+                meta,
+                Some(audit_diff),
+            )
+            .await;
             Ok((StatusCode::CREATED, Json(note_with_user).into_response()))
         } else {
-            let _ = tokio::fs::remove_file(file_path).await;
-            let _ = tokio::fs::remove_file(preview_path).await;
+            let _ = state.env_vars.storage.delete(&file_key).await;
             Err(NoteError::UploadFailed(
                 "Failed to save note to database".to_string(),
             ))?
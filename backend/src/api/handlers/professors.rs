@@ -0,0 +1,108 @@
+use crate::api::errors::{AppError, ProfessorError};
+use crate::api::handlers::notes::build_response_notes;
+use crate::api::router::RouterState;
+use crate::db::handlers::professors::{
+    find_similar_professors, get_professor, get_professor_notes, get_professors,
+};
+use crate::db::models::{ProfessorCandidate, User};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Most names that can be checked in one request
+const MAX_SUGGESTION_NAMES: usize = 10;
+/// Most professors suggested for each name
+const MAX_CANDIDATES: usize = 5;
+
+#[derive(Deserialize)]
+pub struct ProfessorQuery {
+    pub query: Option<String>,
+    pub num: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ProfessorSuggestQuery {
+    /// Comma-separated professor names, as entered in the upload form
+    pub names: String,
+}
+
+#[derive(Serialize)]
+pub struct ProfessorSuggestion {
+    pub name: String,
+    /// Whether the name is exactly an existing professor's name or alias, so it will be linked to them
+    pub exact_match: bool,
+    /// Existing professors with similar names, for the form to offer instead of creating a new professor
+    pub candidates: Vec<ProfessorCandidate>,
+}
+
+/// API handler to list professors, optionally fuzzy matched against a name for autocompletion.
+pub async fn list_professors(
+    State(state): State<RouterState>,
+    Query(query): Query<ProfessorQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let search = query
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty());
+
+    let professors = get_professors(&state.db_wrapper, search, query.num.unwrap_or(50))
+        .await
+        .map_err(|e| {
+            ProfessorError::DatabaseError("Failed to fetch professors".to_string(), e.into())
+        })?;
+
+    Ok((StatusCode::OK, Json(professors).into_response()))
+}
+
+/// API handler to check the professor names of an upload, suggesting existing professors for
+/// names that would otherwise create a new one.
+pub async fn suggest_professors(
+    State(state): State<RouterState>,
+    Query(query): Query<ProfessorSuggestQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let mut suggestions = Vec::new();
+    for name in query
+        .names
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .take(MAX_SUGGESTION_NAMES)
+    {
+        let candidates = find_similar_professors(&state.db_wrapper, name, MAX_CANDIDATES)
+            .await
+            .map_err(|e| {
+                ProfessorError::DatabaseError("Failed to fetch professors".to_string(), e.into())
+            })?;
+
+        let exact_match = candidates.iter().any(|candidate| candidate.exact);
+        suggestions.push(ProfessorSuggestion {
+            name: name.to_string(),
+            exact_match,
+            candidates: if exact_match { Vec::new() } else { candidates },
+        });
+    }
+
+    Ok((StatusCode::OK, Json(suggestions).into_response()))
+}
+
+/// API handler to list the notes of a professor.
+pub async fn professor_notes(
+    State(state): State<RouterState>,
+    Extension(user): Extension<Option<User>>,
+    Path(professor_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let professor = get_professor(&state.db_wrapper, professor_id)
+        .await
+        .map_err(|e| ProfessorError::DatabaseError("Failed to fetch professor".to_string(), e.into()))?
+        .ok_or_else(|| ProfessorError::NotFound("Professor not found".to_string()))?;
+
+    let notes = get_professor_notes(&state.db_wrapper, professor.id, user.as_ref().map(|u| u.id))
+        .await
+        .map_err(|e| ProfessorError::DatabaseError("Failed to fetch notes".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(build_response_notes(&state, notes)).into_response()))
+}
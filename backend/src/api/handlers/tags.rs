@@ -0,0 +1,69 @@
+use crate::api::errors::{AppError, TagError};
+use crate::api::router::RouterState;
+use crate::db::handlers::tags::get_tags;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+
+const MAX_TAGS: usize = 500;
+
+/// Normalizes user-entered tags, e.g. " Data  Structures" becomes "data structures".
+pub(crate) fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Normalizes a list of tags, dropping empty and repeated ones.
+pub(crate) fn parse_tags<'a>(data: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in data.into_iter().map(normalize_tag) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+#[derive(Deserialize)]
+pub struct TagQuery {
+    pub num: Option<usize>,
+}
+
+/// API handler to list all tags with their note counts.
+pub async fn list_tags(
+    State(state): State<RouterState>,
+    Query(query): Query<TagQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let tags = get_tags(&state.db_wrapper, None, query.num.unwrap_or(100).min(MAX_TAGS))
+        .await
+        .map_err(|e| TagError::DatabaseError("Failed to fetch tags".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(tags).into_response()))
+}
+
+#[derive(Deserialize)]
+pub struct TagSuggestQuery {
+    pub q: String,
+    pub num: Option<usize>,
+}
+
+/// API handler to autocomplete tags by prefix for the upload form.
+pub async fn suggest_tags(
+    State(state): State<RouterState>,
+    Query(query): Query<TagSuggestQuery>,
+) -> Result<(StatusCode, Response), AppError> {
+    let prefix = normalize_tag(&query.q);
+    if prefix.is_empty() {
+        return Err(TagError::InvalidData("Query cannot be empty".to_string()).into());
+    }
+
+    let tags = get_tags(&state.db_wrapper, Some(&prefix), query.num.unwrap_or(10).min(MAX_TAGS))
+        .await
+        .map_err(|e| TagError::DatabaseError("Failed to fetch tags".to_string(), e.into()))?;
+
+    Ok((StatusCode::OK, Json(tags).into_response()))
+}
@@ -0,0 +1,322 @@
+use crate::api::audit::RequestMeta;
+use crate::api::errors::{AppError, NoteError, UploadSessionError};
+use crate::api::handlers::notes::{prepare_note, process_upload};
+use crate::api::models::{CreateUploadSession, NoteUploadFields, ResponseUploadSession};
+use crate::api::router::RouterState;
+use crate::db::handlers::uploads::{
+    create_upload_session, delete_upload_session, get_upload_session, lock_upload_session,
+    mark_upload_session_completing, reset_upload_session_completing, set_upload_session_progress,
+};
+use crate::db::models::{DBUploadSession, User};
+use crate::upload::{session_file_path, write_session_chunk, TempUpload};
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Largest chunk accepted in a single request
+const MAX_CHUNK_SIZE: usize = 16 << 20;
+/// Upload sessions expire this long after their last chunk
+const SESSION_LIFETIME_HOURS: i64 = 24;
+/// Most unexpired upload sessions a user can have, as each one can take up to the file size limit on disk
+const MAX_OPEN_SESSIONS: i64 = 3;
+
+#[derive(Deserialize)]
+pub struct ChunkQuery {
+    pub offset: i64,
+}
+
+impl From<DBUploadSession> for ResponseUploadSession {
+    fn from(session: DBUploadSession) -> Self {
+        Self {
+            id: session.id,
+            size: session.total_size,
+            offset: session.received_size,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+fn check_not_expired(session: &DBUploadSession) -> Result<(), UploadSessionError> {
+    if session.expires_at < Utc::now() {
+        return Err(UploadSessionError::Expired(
+            "Upload session has expired, please start the upload again".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_not_completing(session: &DBUploadSession) -> Result<(), UploadSessionError> {
+    if session.completing {
+        return Err(UploadSessionError::Completing(
+            "Upload is already being completed".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// API handler to start a resumable upload. The note's details are validated up front, so that
+/// the file isn't uploaded only to be rejected.
+pub async fn start_upload(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Json(body): Json<CreateUploadSession>,
+) -> Result<(StatusCode, Response), AppError> {
+    let file_size_limit = (state.env_vars.file_size_limit << 20) as i64;
+    if body.size <= 0 {
+        return Err(UploadSessionError::InvalidData("File size must be positive".to_string()))?;
+    }
+    if body.size > file_size_limit {
+        return Err(UploadSessionError::InvalidData(format!(
+            "File size too big. Only files up to {} MiB are allowed.",
+            file_size_limit >> 20
+        )))?;
+    }
+
+    prepare_note(&state, body.note.clone(), user.id, String::new()).await?;
+    let metadata = serde_json::to_value(&body.note).map_err(|err| {
+        UploadSessionError::UploadFailed(format!("Failed to save note details: {}", err))
+    })?;
+
+    let session = create_upload_session(
+        &state.db_wrapper,
+        user.id,
+        body.size,
+        metadata,
+        Utc::now() + Duration::hours(SESSION_LIFETIME_HOURS),
+        MAX_OPEN_SESSIONS,
+    )
+    .await
+    .map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to start upload".to_string(), err.into())
+    })?
+    .ok_or(UploadSessionError::TooManySessions(format!(
+        "Only {} uploads can be in progress at once, finish or cancel one first",
+        MAX_OPEN_SESSIONS
+    )))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ResponseUploadSession::from(session)).into_response(),
+    ))
+}
+
+/// API handler to get the progress of an upload, e.g. to find where to resume it from.
+pub async fn get_upload(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let session = get_upload_session(&state.db_wrapper, upload_id, user.id)
+        .await
+        .map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to fetch upload".to_string(), err.into())
+        })?
+        .ok_or(UploadSessionError::NotFound("Upload not found".to_string()))?;
+    check_not_expired(&session)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseUploadSession::from(session)).into_response(),
+    ))
+}
+
+/// API handler to append a chunk to an upload. Chunks must be sent in order, starting at the
+/// offset the upload has reached.
+pub async fn upload_chunk(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+    Query(query): Query<ChunkQuery>,
+    chunk: Bytes,
+) -> Result<(StatusCode, Response), AppError> {
+    if chunk.is_empty() {
+        return Err(UploadSessionError::InvalidData("Chunk is empty".to_string()))?;
+    }
+    if chunk.len() > MAX_CHUNK_SIZE {
+        return Err(UploadSessionError::InvalidData(format!(
+            "Chunk too big. Only chunks up to {} MiB are allowed.",
+            MAX_CHUNK_SIZE >> 20
+        )))?;
+    }
+
+    let (mut tx, session) = lock_upload_session(&state.db_wrapper, upload_id, user.id)
+        .await
+        .map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to fetch upload".to_string(), err.into())
+        })?;
+    let session = session.ok_or(UploadSessionError::NotFound("Upload not found".to_string()))?;
+    check_not_expired(&session)?;
+    check_not_completing(&session)?;
+
+    if query.offset != session.received_size {
+        return Err(UploadSessionError::OffsetMismatch(session.received_size))?;
+    }
+    let received_size = session.received_size + chunk.len() as i64;
+    if received_size > session.total_size {
+        return Err(UploadSessionError::InvalidData(format!(
+            "Chunk goes past the end of the file, which is {} bytes",
+            session.total_size
+        )))?;
+    }
+
+    let file_path = session_file_path(state.env_vars.paths.get_notes_dir(), session.id);
+    write_session_chunk(&file_path, session.received_size as u64, &chunk)
+        .await
+        .map_err(|err| {
+            tracing::error!("Failed to write chunk of upload {}: {}", session.id, err);
+            UploadSessionError::UploadFailed("Failed to save chunk".to_string())
+        })?;
+
+    let session = set_upload_session_progress(
+        &mut tx,
+        session.id,
+        received_size,
+        Utc::now() + Duration::hours(SESSION_LIFETIME_HOURS),
+    )
+    .await
+    .map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to save upload progress".to_string(), err.into())
+    })?;
+    tx.commit().await.map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to save upload progress".to_string(), err.into())
+    })?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ResponseUploadSession::from(session)).into_response(),
+    ))
+}
+
+/// Why a completed upload didn't become a note. `AppError` isn't `Send`, so it can't be held while
+/// the upload session is updated.
+enum UploadFailure {
+    /// The file is already a note, so it can never go through
+    Duplicate(Uuid, String),
+    /// The file is infected, so it can never go through
+    Infected(String),
+    /// Creating the note failed for another reason, given as the error's response
+    Failed(Response),
+}
+
+/// API handler to complete an upload once all of its chunks have been received, creating the note.
+///
+/// The session is only locked while it's marked as completing, not while the note is created, which
+/// can take minutes. If creating the note fails, the mark is cleared so that completing it can be retried.
+pub async fn complete_upload(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    meta: RequestMeta,
+    Path(upload_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let (mut tx, session) = lock_upload_session(&state.db_wrapper, upload_id, user.id)
+        .await
+        .map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to fetch upload".to_string(), err.into())
+        })?;
+    let session = session.ok_or(UploadSessionError::NotFound("Upload not found".to_string()))?;
+    check_not_expired(&session)?;
+    check_not_completing(&session)?;
+
+    if session.received_size != session.total_size {
+        return Err(UploadSessionError::InvalidData(format!(
+            "Upload is incomplete, received {} of {} bytes",
+            session.received_size, session.total_size
+        )))?;
+    }
+
+    let fields: NoteUploadFields = serde_json::from_value(session.metadata).map_err(|err| {
+        UploadSessionError::UploadFailed(format!("Failed to read note details: {}", err))
+    })?;
+
+    mark_upload_session_completing(
+        &mut tx,
+        session.id,
+        Utc::now() + Duration::hours(SESSION_LIFETIME_HOURS),
+    )
+    .await
+    .map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to complete upload".to_string(), err.into())
+    })?;
+    tx.commit().await.map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to complete upload".to_string(), err.into())
+    })?;
+
+    let file_path = session_file_path(state.env_vars.paths.get_notes_dir(), session.id);
+    let uploaded_file = match TempUpload::from_session_file(file_path.clone()).await {
+        Ok(uploaded_file) => uploaded_file,
+        Err(err) => {
+            tracing::error!("Failed to read file of upload {}: {}", session.id, err);
+            release_upload_session(&state, session.id).await;
+            return Err(UploadSessionError::UploadFailed("Failed to read uploaded file".to_string()))?;
+        }
+    };
+
+    let result = match process_upload(&state, &user, &meta, fields, vec![uploaded_file]).await {
+        Ok(response) => Ok(response),
+        Err(AppError::Note(NoteError::Duplicate(note_id, file_url))) => {
+            Err(UploadFailure::Duplicate(note_id, file_url))
+        }
+        Err(AppError::Note(NoteError::Infected(msg))) => Err(UploadFailure::Infected(msg)),
+        Err(err) => Err(UploadFailure::Failed(err.into_response())),
+    };
+
+    if matches!(result, Err(UploadFailure::Failed(_))) {
+        release_upload_session(&state, session.id).await;
+    } else {
+        // Either the note was created or the file will never go through, so the upload is no longer needed
+        let mut tx = state.db_wrapper.pool().begin().await.map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to remove upload".to_string(), err.into())
+        })?;
+        delete_upload_session(&mut tx, session.id).await.map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to remove upload".to_string(), err.into())
+        })?;
+        tx.commit().await.map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to remove upload".to_string(), err.into())
+        })?;
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+
+    match result {
+        Ok(response) => Ok(response),
+        Err(UploadFailure::Duplicate(note_id, file_url)) => Err(NoteError::Duplicate(note_id, file_url).into()),
+        Err(UploadFailure::Infected(msg)) => Err(NoteError::Infected(msg).into()),
+        Err(UploadFailure::Failed(response)) => Ok((response.status(), response)),
+    }
+}
+
+/// Clears the completing mark of an upload whose note couldn't be created.
+async fn release_upload_session(state: &RouterState, session_id: Uuid) {
+    if let Err(err) = reset_upload_session_completing(&state.db_wrapper, session_id).await {
+        tracing::error!("Failed to release upload {}: {}", session_id, err);
+    }
+}
+
+/// API handler to abort an upload, discarding the chunks received so far.
+pub async fn abort_upload(
+    State(state): State<RouterState>,
+    Extension(user): Extension<User>,
+    Path(upload_id): Path<Uuid>,
+) -> Result<(StatusCode, Response), AppError> {
+    let (mut tx, session) = lock_upload_session(&state.db_wrapper, upload_id, user.id)
+        .await
+        .map_err(|err| {
+            UploadSessionError::DatabaseError("Failed to fetch upload".to_string(), err.into())
+        })?;
+    let session = session.ok_or(UploadSessionError::NotFound("Upload not found".to_string()))?;
+    check_not_completing(&session)?;
+
+    delete_upload_session(&mut tx, session.id).await.map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to remove upload".to_string(), err.into())
+    })?;
+    tx.commit().await.map_err(|err| {
+        UploadSessionError::DatabaseError("Failed to remove upload".to_string(), err.into())
+    })?;
+    let _ = tokio::fs::remove_file(session_file_path(state.env_vars.paths.get_notes_dir(), session.id)).await;
+
+    Ok((StatusCode::OK, Json("OK").into_response()))
+}
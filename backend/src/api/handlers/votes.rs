@@ -3,42 +3,42 @@ use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use serde::Deserialize;
+use serde_json::json;
+use crate::api::audit::{record_event, AuditAction, RequestMeta};
 use crate::api::errors::{AppError, NoteError};
 use crate::api::router::RouterState;
-use crate::db::models::User;
+use crate::db::models::{User, VoteType};
 use crate::db::handlers::votes::vote;
 
-#[derive(Deserialize)]
-pub enum VoteType {
-    Upvote(bool),
-    Remove
-}
-
 #[derive(Deserialize)]
 pub struct VoteRequest {
-    vote_type: String,
+    pub(crate) vote_type: String,
 }
 
 pub async fn add_vote (
     State(state): State<RouterState>,
     Extension(user): Extension<User>,
     Path(note_id): Path<uuid::Uuid>,
+    meta: RequestMeta,
     Query(query): Query<VoteRequest>,
 ) -> Result<(StatusCode, Response), AppError> {
-    let vote_type = match query.vote_type.as_str() {
-        "upvote" => VoteType::Upvote(true),
-        // Remove
-        // "downvote" => VoteType::Upvote(false),
-        "remove" => VoteType::Remove,
-        _ => {
-            return Err(NoteError::BadVote(format!("Incorrect vote type: {}. Available options are: upvote and remove", query.vote_type)).into());
-        }
-    };
+    let vote_type = VoteType::from_query(&query.vote_type).ok_or_else(|| {
+        NoteError::BadVote(format!("Incorrect vote type: {}. Available options are: upvote and remove", query.vote_type))
+    })?;
     let v = vote(&state.db_wrapper, user.id, note_id, vote_type)
         .await
         .map_err(|e| {
             tracing::error!("Failed to add vote: {}", e);
             NoteError::DatabaseError("Failed to add vote".to_string(), e.into())
         })?;
+    record_event(
+        &state,
+        Some(user.id),
+        AuditAction::NoteVote,
+        Some(note_id),
+        &meta,
+        Some(json!({ "vote_type": query.vote_type, "after": v })),
+    )
+    .await;
     Ok((StatusCode::OK, Json(v).into_response()))
 }
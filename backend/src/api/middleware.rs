@@ -4,6 +4,7 @@ use crate::db;
 use crate::db::models::User;
 use axum::body::Body;
 use axum::extract::{Request, State};
+use axum::Extension;
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::Response;
@@ -127,4 +128,23 @@ pub(crate) async fn optional_auth_middleware(
     let response = next.run(request).await;
 
     Ok(response)
-}
\ No newline at end of file
+}
+
+// Admin-only middleware, must run after `verify_token_middleware`
+pub(crate) async fn require_admin_middleware(
+    Extension(user): Extension<User>,
+    request: Request<Body>,
+    next: Next,
+) -> Result<Response<Body>, AppError> {
+    if !user.is_admin {
+        tracing::debug!("Non-admin user {} attempted to access an admin route", user.id);
+        return Ok(create_cors_error_response(
+            StatusCode::FORBIDDEN,
+            "Admin access required"
+        ));
+    }
+
+    let response = next.run(request).await;
+
+    Ok(response)
+}
@@ -1,5 +1,7 @@
+pub(crate) mod audit;
 pub(crate) mod handlers;
 pub(crate) mod router;
 pub(crate) mod errors;
 pub mod middleware;
 pub(crate) mod models;
+pub(crate) mod signed_urls;
@@ -1,20 +1,70 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
-use crate::db::models::NoteWithUser;
+use crate::db::models::{CollectionWithOwner, CommentWithAuthor, ContentType, NoteWithUser, Semester};
+use crate::preview::{PreviewFormat, PreviewSize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreateNote {
     pub course_name: String,
     pub course_code: String,
+    pub course_id: Option<Uuid>,
     pub description: Option<String>,
     pub professor_names: Option<Vec<String>>,
     pub tags: Vec<String>,
+    pub academic_year: Option<i32>,
+    pub semester: Option<Semester>,
+    pub content_type: Option<ContentType>,
+    pub file_sha256: String,
+    pub page_count: Option<i32>,
+    pub pdf_metadata: Option<serde_json::Value>,
     pub has_preview_image: bool,
     pub uploader_user_id: Uuid,
     pub timestamp: DateTime<Utc>,
 }
 
+/// Details sent along with an uploaded note, before validation.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct NoteUploadFields {
+    #[serde(default)]
+    pub course_name: String,
+    pub course_code: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub professor_names: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub academic_year: Option<i32>,
+    pub semester: Option<Semester>,
+    pub content_type: Option<ContentType>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateUploadSession {
+    /// Size of the whole file in bytes
+    pub size: i64,
+    #[serde(flatten)]
+    pub note: NoteUploadFields,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseUploadSession {
+    pub id: Uuid,
+    pub size: i64,
+    /// Number of bytes received so far, where the next chunk starts
+    pub offset: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Metadata filters shared by the note listing endpoints.
+#[derive(Deserialize, Debug, Default)]
+pub struct NoteFilter {
+    pub academic_year: Option<i32>,
+    pub semester: Option<Semester>,
+    pub content_type: Option<ContentType>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ResponseUser {
     pub id: Uuid,
@@ -33,23 +83,57 @@ pub struct ResponseNote {
     pub description: Option<String>,
     pub professor_names: Option<Vec<String>>,
     pub tags: Vec<String>,
+    pub academic_year: Option<i32>,
+    pub semester: Option<Semester>,
+    pub content_type: Option<ContentType>,
+    pub page_count: Option<i32>,
     pub is_public: bool,
     pub has_preview_image: bool,
     pub preview_image_url: String,
+    /// Previews of the first pages, one per page and size
+    pub previews: Vec<ResponsePreview>,
     pub file_url: String,
     pub uploader_user: ResponseUser,
     pub created_at: DateTime<Utc>,
     pub upvotes: usize,
     pub downvotes: usize,
+    pub comment_count: usize,
     pub user_vote: Option<bool>, // If currently authenticated user has voted on this note
+    pub bookmarked: bool, // If currently authenticated user has bookmarked this note
     pub downloads: usize,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponsePreview {
+    pub page: i32,
+    pub size: PreviewSize,
+    /// URL of the JPEG preview, which every client supports
+    pub url: String,
+    /// URLs of the same preview in smaller formats, in order of preference
+    pub variants: BTreeMap<PreviewFormat, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseSearch {
+    pub notes: Vec<ResponseNote>,
+    /// Whether typo-tolerant matches were added because the exact search found few notes
+    pub fuzzy: bool,
+    pub did_you_mean: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseRelatedNotes {
+    pub same_course: Vec<ResponseNote>,
+    pub same_professor: Vec<ResponseNote>,
+    pub also_liked: Vec<ResponseNote>,
+}
+
 impl ResponseNote {
     pub fn from_note_with_user(
         note: NoteWithUser,
         file_url: String,
         preview_image_url: String,
+        previews: Vec<ResponsePreview>,
     ) -> Self {
         Self {
             id: note.note_id,
@@ -58,14 +142,21 @@ impl ResponseNote {
             description: note.note_description,
             professor_names: note.note_professor_names,
             tags: note.note_tags,
+            academic_year: note.note_academic_year,
+            semester: note.note_semester,
+            content_type: note.note_content_type,
+            page_count: note.note_page_count,
             is_public: note.note_is_public,
             has_preview_image: note.note_has_preview_image,
             preview_image_url,
+            previews,
             file_url,
             upvotes: note.note_upvote_count as usize,
             downvotes: note.note_downvote_count as usize,
+            comment_count: note.note_comment_count as usize,
             downloads: note.note_downloads as usize,
             user_vote: note.note_user_upvote,
+            bookmarked: note.note_user_bookmarked,
             uploader_user: ResponseUser {
                 id: note.user_id,
                 google_id: note.user_google_id,
@@ -77,4 +168,164 @@ impl ResponseNote {
             created_at: note.note_created_at,
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateAuditEvent {
+    pub actor_user_id: Option<Uuid>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub diff: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditEventFilter {
+    pub actor_user_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub action: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateCollection {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub owner_user_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UpdateCollection {
+    pub name: Option<String>,
+    /// `Some(None)` clears the description
+    pub description: Option<Option<String>>,
+    pub is_public: Option<bool>,
+}
+
+/// Which collections to fetch. Private collections are only returned to their owner.
+#[derive(Debug, Default)]
+pub struct CollectionFilter {
+    pub collection_id: Option<Uuid>,
+    pub share_token: Option<Uuid>,
+    pub owner_user_id: Option<Uuid>,
+    pub search_term: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseCollection {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub share_token: Option<Uuid>, // Only visible to the owner
+    pub owner: ResponseUser,
+    pub note_count: usize,
+    pub upvotes: usize,
+    pub downvotes: usize,
+    pub user_vote: Option<bool>, // If currently authenticated user has voted on this collection
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ResponseCollection {
+    pub fn from_collection_with_owner(
+        collection: CollectionWithOwner,
+        current_user_id: Option<Uuid>,
+    ) -> Self {
+        let is_owner = current_user_id == Some(collection.collection_owner_user_id);
+        Self {
+            id: collection.collection_id,
+            name: collection.collection_name,
+            description: collection.collection_description,
+            is_public: collection.collection_is_public,
+            share_token: is_owner.then_some(collection.collection_share_token),
+            owner: ResponseUser {
+                id: collection.user_id,
+                google_id: collection.user_google_id,
+                email: collection.user_email,
+                full_name: collection.user_full_name,
+                reputation: collection.user_reputation,
+                created_at: collection.user_created_at,
+            },
+            note_count: collection.collection_note_count as usize,
+            upvotes: collection.collection_upvote_count as usize,
+            downvotes: collection.collection_downvote_count as usize,
+            user_vote: collection.collection_user_upvote,
+            created_at: collection.collection_created_at,
+            updated_at: collection.collection_updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseCollectionWithNotes {
+    #[serde(flatten)]
+    pub collection: ResponseCollection,
+    pub notes: Vec<ResponseNote>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateComment {
+    pub note_id: Uuid,
+    pub author_user_id: Uuid,
+    pub parent_comment_id: Option<Uuid>,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ResponseComment {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: Option<String>, // Hidden once the comment is deleted or removed
+    pub author: ResponseUser,
+    pub is_deleted: bool,
+    pub is_removed: bool, // Removed by a moderator rather than the author
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub replies: Vec<ResponseComment>,
+}
+
+impl ResponseComment {
+    pub fn from_comment_with_author(comment: CommentWithAuthor) -> Self {
+        let is_deleted = comment.comment_deleted_at.is_some();
+        Self {
+            id: comment.comment_id,
+            note_id: comment.comment_note_id,
+            parent_id: comment.comment_parent_comment_id,
+            body: (!is_deleted).then_some(comment.comment_body),
+            author: ResponseUser {
+                id: comment.user_id,
+                google_id: comment.user_google_id,
+                email: comment.user_email,
+                full_name: comment.user_full_name,
+                reputation: comment.user_reputation,
+                created_at: comment.user_created_at,
+            },
+            is_deleted,
+            is_removed: comment.comment_removed_by_user_id.is_some(),
+            created_at: comment.comment_created_at,
+            edited_at: comment.comment_edited_at,
+            replies: Vec::new(),
+        }
+    }
+}
+
+/// Adds a spelling that resolves to a professor. It is normalized before being stored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateProfessorAlias {
+    pub alias: String,
+}
+
+/// Merges `synonym` into `tag`. Both are normalized before being stored.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateTagSynonym {
+    pub synonym: String,
+    pub tag: String,
+}
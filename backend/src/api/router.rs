@@ -2,22 +2,30 @@
 
 use super::handlers;
 use crate::api::middleware;
+use crate::cache::TtlCache;
+use crate::db::models::{RelatedNoteIds, Suggestion};
 use crate::db::DBPoolWrapper;
 use crate::env::EnvVars;
-use axum::middleware::from_fn_with_state;
+use axum::middleware::{from_fn, from_fn_with_state};
 use axum::{
     http::{  StatusCode},
-    routing::{get, post, options},
+    routing::{delete, get, post, put, options},
     Router,
     response::Response,
     body::Body,
 };
-use tower_http::services::{ServeDir, ServeFile};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use tower_http::services::ServeDir;
 
 #[derive(Clone)]
 pub(crate) struct RouterState {
     pub db_wrapper: DBPoolWrapper,
-    pub env_vars: EnvVars
+    pub env_vars: EnvVars,
+    /// Search suggestions keyed by normalized query and limit
+    pub suggestion_cache: Arc<TtlCache<(String, usize), Vec<Suggestion>>>,
+    pub related_notes_cache: Arc<TtlCache<Uuid, RelatedNoteIds>>,
 }
 
 // Handler for preflight OPTIONS requests
@@ -36,24 +44,118 @@ pub fn create_router(db_wrapper: DBPoolWrapper, env_vars: EnvVars) -> Router {
     let state = RouterState {
         db_wrapper,
         env_vars,
+        suggestion_cache: Arc::new(TtlCache::new(Duration::from_secs(60), 10_000)),
+        related_notes_cache: Arc::new(TtlCache::new(Duration::from_secs(600), 10_000)),
     };
 
     // Handle OPTIONS requests first, without any middleware
     let options_router = Router::new()
         .route("/notes/upload", options(handle_options))
+        .route("/uploads", options(handle_options))
+        .route("/uploads/{upload_id}", options(handle_options))
+        .route("/uploads/{upload_id}/chunks", options(handle_options))
+        .route("/uploads/{upload_id}/complete", options(handle_options))
         .route("/notes/{note_id}/vote", options(handle_options))
+        .route("/notes/{note_id}/bookmark", options(handle_options))
+        .route("/notes/{note_id}/comments", options(handle_options))
+        .route("/comments/{comment_id}", options(handle_options))
         .route("/auth/me", options(handle_options))
+        .route("/auth/me/bookmarks", options(handle_options))
+        .route("/auth/me/collections", options(handle_options))
+        .route("/collections", options(handle_options))
+        .route("/collections/search", options(handle_options))
+        .route("/collections/shared/{share_token}", options(handle_options))
+        .route("/collections/{collection_id}", options(handle_options))
+        .route("/collections/{collection_id}/notes", options(handle_options))
+        .route("/collections/{collection_id}/notes/order", options(handle_options))
+        .route("/collections/{collection_id}/notes/{note_id}", options(handle_options))
+        .route("/collections/{collection_id}/vote", options(handle_options))
         .route("/notes", options(handle_options))
         .route("/notes/search", options(handle_options))
+        .route("/notes/suggest", options(handle_options))
         .route("/notes/{note_id}", options(handle_options))
+        .route("/notes/{note_id}/related", options(handle_options))
         .route("/auth/google", options(handle_options))
-        .route("/notes/{note_id}/download", options(handle_options));
+        .route("/courses", options(handle_options))
+        .route("/courses/{code}/notes", options(handle_options))
+        .route("/professors", options(handle_options))
+        .route("/professors/suggest", options(handle_options))
+        .route("/tags", options(handle_options))
+        .route("/tags/suggest", options(handle_options))
+        .route("/admin/tags/synonyms", options(handle_options))
+        .route("/admin/tags/synonyms/{synonym}", options(handle_options))
+        .route("/professors/{professor_id}/notes", options(handle_options))
+        .route("/admin/audit", options(handle_options))
+        .route("/admin/courses/import", options(handle_options))
+        .route("/admin/duplicates", options(handle_options))
+        .route("/admin/previews/backfill", options(handle_options))
+        .route("/admin/professors/{professor_id}/aliases", options(handle_options))
+        .route("/admin/quarantine", options(handle_options))
+        .route("/admin/duplicates/{duplicate_id}/merge", options(handle_options))
+        .route("/admin/duplicates/{duplicate_id}/dismiss", options(handle_options))
+        .route("/notes/{note_id}/download", options(handle_options))
+        .route("/files/{*key}", options(handle_options));
 
     // Protected routes without options handlers
     let protected_router = Router::new()
         .route("/notes/upload", post(handlers::notes::upload_note))
+        .route("/uploads", post(handlers::uploads::start_upload))
+        .route(
+            "/uploads/{upload_id}",
+            get(handlers::uploads::get_upload).delete(handlers::uploads::abort_upload),
+        )
+        .route("/uploads/{upload_id}/chunks", post(handlers::uploads::upload_chunk))
+        .route("/uploads/{upload_id}/complete", post(handlers::uploads::complete_upload))
         .route("/notes/{note_id}/vote", post(handlers::votes::add_vote))
+        .route(
+            "/notes/{note_id}/bookmark",
+            post(handlers::bookmarks::bookmark_note).delete(handlers::bookmarks::unbookmark_note),
+        )
+        .route("/notes/{note_id}/comments", post(handlers::comments::add_comment))
+        .route(
+            "/comments/{comment_id}",
+            put(handlers::comments::edit_comment).delete(handlers::comments::remove_comment),
+        )
         .route("/auth/me", get(handlers::auth::get_current_user))
+        .route("/auth/me/bookmarks", get(handlers::bookmarks::list_bookmarks))
+        .route("/auth/me/collections", get(handlers::collections::my_collections))
+        .route("/collections", post(handlers::collections::create_new_collection))
+        .route(
+            "/collections/{collection_id}",
+            put(handlers::collections::update_existing_collection)
+                .delete(handlers::collections::delete_existing_collection),
+        )
+        .route("/collections/{collection_id}/notes", post(handlers::collections::add_collection_note))
+        .route("/collections/{collection_id}/notes/order", put(handlers::collections::reorder_collection))
+        .route(
+            "/collections/{collection_id}/notes/{note_id}",
+            delete(handlers::collections::remove_collection_note),
+        )
+        .route("/collections/{collection_id}/vote", post(handlers::collections::add_collection_vote))
+        .route_layer(from_fn_with_state(
+            state.clone(),
+            middleware::verify_token_middleware,
+        ));
+
+    // Admin routes, the token is verified before the admin check runs
+    let admin_router = Router::new()
+        .route("/admin/audit", get(handlers::admin::list_audit_events))
+        .route("/admin/courses/import", post(handlers::admin::import_courses))
+        .route("/admin/duplicates", get(handlers::admin::list_duplicates))
+        .route("/admin/duplicates/{duplicate_id}/merge", post(handlers::admin::merge_duplicate))
+        .route("/admin/duplicates/{duplicate_id}/dismiss", post(handlers::admin::dismiss_duplicate))
+        .route("/admin/previews/backfill", post(handlers::admin::backfill_note_previews))
+        .route(
+            "/admin/professors/{professor_id}/aliases",
+            post(handlers::admin::create_professor_alias),
+        )
+        .route("/admin/quarantine", get(handlers::admin::list_quarantined_files))
+        .route(
+            "/admin/tags/synonyms",
+            get(handlers::admin::list_tag_synonyms).post(handlers::admin::create_tag_synonym),
+        )
+        .route("/admin/tags/synonyms/{synonym}", delete(handlers::admin::delete_tag_synonym))
+        .route_layer(from_fn(middleware::require_admin_middleware))
         .route_layer(from_fn_with_state(
             state.clone(),
             middleware::verify_token_middleware,
@@ -63,6 +165,13 @@ pub fn create_router(db_wrapper: DBPoolWrapper, env_vars: EnvVars) -> Router {
         .route("/notes", get(handlers::notes::list_notes))
         .route("/notes/search", get(handlers::notes::search_notes))
         .route("/notes/{note_id}", get(handlers::notes::note_by_id))
+        .route("/notes/{note_id}/related", get(handlers::notes::related_notes))
+        .route("/courses/{code}/notes", get(handlers::courses::course_notes))
+        .route("/professors/{professor_id}/notes", get(handlers::professors::professor_notes))
+        .route("/collections", get(handlers::collections::list_collections))
+        .route("/collections/search", get(handlers::collections::search_collections))
+        .route("/collections/shared/{share_token}", get(handlers::collections::collection_by_share_token))
+        .route("/collections/{collection_id}", get(handlers::collections::collection_by_id))
         .route_layer(from_fn_with_state(
             state.clone(),
             middleware::optional_auth_middleware,
@@ -71,21 +180,29 @@ pub fn create_router(db_wrapper: DBPoolWrapper, env_vars: EnvVars) -> Router {
     let public_router = Router::new()
         .route("/", get(handlers::misc::index))
         .route("/auth/google", post(handlers::auth::google_auth_callback))
-        .route("/notes/{note_id}/download", get(handlers::notes::download_note));
+        .route("/courses", get(handlers::courses::list_courses))
+        .route("/professors", get(handlers::professors::list_professors))
+        .route("/professors/suggest", get(handlers::professors::suggest_professors))
+        .route("/notes/suggest", get(handlers::notes::suggest))
+        .route("/tags", get(handlers::tags::list_tags))
+        .route("/tags/suggest", get(handlers::tags::suggest_tags))
+        .route("/notes/{note_id}/download", get(handlers::notes::download_note))
+        .route("/notes/{note_id}/comments", get(handlers::comments::list_comments))
+        .route("/files/{*key}", get(handlers::files::download_file));
 
     // Merge routers with OPTIONS first (highest precedence)
     let api_router = Router::new()
         .merge(options_router)  
         .merge(public_router)
         .merge(protected_router)
+        .merge(admin_router)
         .merge(optional_user_router);
 
     // ... rest of your code remains the same
-    let notes_path = state.env_vars.paths.get_notes_dir().to_path_buf();
+    // Note PDFs are only served through signed URLs, see `handlers::files`
     let images_path = state.env_vars.paths.get_previews_dir().to_path_buf();
     Router::new()
         .nest("/api", api_router)
-        .nest_service("/notes/uploaded", ServeDir::new(notes_path))
         .nest_service("/previews/uploaded", ServeDir::new(images_path))
         .with_state(state)
 }
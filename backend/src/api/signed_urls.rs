@@ -0,0 +1,43 @@
+//! HMAC-signed, expiring URLs for note files.
+//!
+//! File URLs point at `/api/files/{key}` with an expiry timestamp and a signature over the key
+//! and expiry, keyed with `SIGNING_SECRET`. Links to notes stop working once they expire, instead
+//! of staying public forever once shared.
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+
+use crate::env::EnvVars;
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn file_mac(secret: &str, key: &str, expires: i64) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    // Prefixed so that file signatures can't be mistaken for anything else signed with the secret
+    mac.update(format!("file:{}:{}", key, expires).as_bytes());
+    mac
+}
+
+/// Builds a URL to download the stored file at `key`, valid for `expires_in`.
+pub fn signed_file_url(env_vars: &EnvVars, key: &str, expires_in: Duration) -> String {
+    let expires = Utc::now().timestamp() + expires_in.as_secs() as i64;
+    let signature = hex::encode(file_mac(&env_vars.signing_secret, key, expires).finalize().into_bytes());
+
+    format!(
+        "{}/api/files/{}?expires={}&signature={}",
+        env_vars.public_api_url.trim_end_matches('/'),
+        key,
+        expires,
+        signature
+    )
+}
+
+/// Checks the signature of a file URL, in constant time. Expiry is checked separately.
+pub fn verify_file_signature(secret: &str, key: &str, expires: i64, signature: &str) -> bool {
+    match hex::decode(signature) {
+        Ok(signature) => file_mac(secret, key, expires).verify_slice(&signature).is_ok(),
+        Err(_) => false,
+    }
+}
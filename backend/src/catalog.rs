@@ -0,0 +1,323 @@
+//! Bulk import of the institute course catalog from CSV or JSON.
+//!
+//! Imports upsert courses by code. Courses missing from the import are reported,
+//! and only deleted when explicitly requested.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::db::handlers::courses::{apply_course_import, get_all_courses};
+use crate::db::models::{is_valid_course_code, normalize_course_code, CourseRecord, DBCourse};
+use crate::db::DBPoolWrapper;
+
+#[derive(Debug, Clone, Copy)]
+pub enum CatalogFormat {
+    Csv,
+    Json,
+}
+
+impl CatalogFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(CatalogFormat::Csv),
+            "json" => Some(CatalogFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next()?.trim() {
+            "text/csv" => Some(CatalogFormat::Csv),
+            "application/json" => Some(CatalogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// A row as it appears in the uploaded file, before validation.
+#[derive(Deserialize)]
+struct RawCourseRecord {
+    code: String,
+    name: String,
+    department: Option<String>,
+    credits: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct InvalidRow {
+    /// 1-based row number, not counting the CSV header
+    pub row: usize,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CourseChange {
+    pub code: String,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CatalogImportReport {
+    pub dry_run: bool,
+    pub applied: bool,
+    pub added: Vec<CourseRecord>,
+    pub changed: Vec<CourseChange>,
+    /// Courses in the catalog but missing from the import. Only deleted with `remove_missing`.
+    pub removed: Vec<CourseRecord>,
+    pub unchanged: usize,
+    pub invalid: Vec<InvalidRow>,
+}
+
+impl CatalogImportReport {
+    /// Renders the report as a line-based diff against the current catalog.
+    pub fn to_diff(&self) -> String {
+        let mut diff = String::new();
+        let describe = |course: &CourseRecord| {
+            format!(
+                "{} | {} | {} | {}",
+                course.code,
+                course.name,
+                course.department.as_deref().unwrap_or("-"),
+                course.credits.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string())
+            )
+        };
+
+        for course in &self.added {
+            let _ = writeln!(diff, "+ {}", describe(course));
+        }
+        for change in &self.changed {
+            for field in &change.changes {
+                let _ = writeln!(
+                    diff,
+                    "~ {} {}: {} -> {}",
+                    change.code,
+                    field.field,
+                    field.from.as_deref().unwrap_or("-"),
+                    field.to.as_deref().unwrap_or("-")
+                );
+            }
+        }
+        for course in &self.removed {
+            let _ = writeln!(diff, "- {}", describe(course));
+        }
+        for row in &self.invalid {
+            let _ = writeln!(diff, "! row {}: {}", row.row, row.reason);
+        }
+        let _ = writeln!(
+            diff,
+            "{} added, {} changed, {} missing from import, {} unchanged, {} invalid",
+            self.added.len(),
+            self.changed.len(),
+            self.removed.len(),
+            self.unchanged,
+            self.invalid.len()
+        );
+
+        diff
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Parse(String),
+    Database(sqlx::Error),
+}
+
+impl std::fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::Parse(msg) => write!(f, "Failed to parse course catalog: {}", msg),
+            CatalogError::Database(err) => write!(f, "Database error during course import: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// Parses and validates catalog rows. Invalid rows are returned separately instead of failing the whole file.
+pub fn parse_catalog(
+    data: &[u8],
+    format: CatalogFormat,
+) -> Result<(Vec<CourseRecord>, Vec<InvalidRow>), CatalogError> {
+    let raw_records: Vec<RawCourseRecord> = match format {
+        CatalogFormat::Csv => csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(data)
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|e| CatalogError::Parse(e.to_string()))?,
+        CatalogFormat::Json => {
+            serde_json::from_slice(data).map_err(|e| CatalogError::Parse(e.to_string()))?
+        }
+    };
+
+    let mut records = Vec::new();
+    let mut invalid = Vec::new();
+    let mut seen_codes = HashSet::new();
+
+    for (index, raw) in raw_records.into_iter().enumerate() {
+        let row = index + 1;
+        let code = normalize_course_code(&raw.code);
+        let name = raw.name.trim().to_string();
+
+        let reason = if !is_valid_course_code(&code) {
+            Some(format!("invalid course code '{}'", raw.code))
+        } else if name.is_empty() {
+            Some(format!("missing name for {}", code))
+        } else if !seen_codes.insert(code.clone()) {
+            Some(format!("duplicate course code {}", code))
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => invalid.push(InvalidRow { row, reason }),
+            None => records.push(CourseRecord {
+                code,
+                name,
+                department: raw
+                    .department
+                    .map(|d| d.trim().to_string())
+                    .filter(|d| !d.is_empty()),
+                credits: raw.credits,
+            }),
+        }
+    }
+
+    Ok((records, invalid))
+}
+
+fn course_record(course: &DBCourse) -> CourseRecord {
+    CourseRecord {
+        code: course.code.clone(),
+        name: course.name.clone(),
+        department: course.department.clone(),
+        credits: course.credits,
+    }
+}
+
+/// Compares the incoming catalog against the current one.
+pub fn diff_catalog(
+    current: &[DBCourse],
+    incoming: &[CourseRecord],
+    invalid: Vec<InvalidRow>,
+    dry_run: bool,
+) -> CatalogImportReport {
+    let current_by_code: HashMap<&str, CourseRecord> = current
+        .iter()
+        .map(|course| (course.code.as_str(), course_record(course)))
+        .collect();
+    let incoming_codes: HashSet<&str> = incoming.iter().map(|c| c.code.as_str()).collect();
+
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+
+    for course in incoming {
+        let Some(existing) = current_by_code.get(course.code.as_str()) else {
+            added.push(course.clone());
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if existing.name != course.name {
+            changes.push(FieldChange {
+                field: "name",
+                from: Some(existing.name.clone()),
+                to: Some(course.name.clone()),
+            });
+        }
+        if existing.department != course.department {
+            changes.push(FieldChange {
+                field: "department",
+                from: existing.department.clone(),
+                to: course.department.clone(),
+            });
+        }
+        if existing.credits != course.credits {
+            changes.push(FieldChange {
+                field: "credits",
+                from: existing.credits.map(|c| c.to_string()),
+                to: course.credits.map(|c| c.to_string()),
+            });
+        }
+
+        if changes.is_empty() {
+            unchanged += 1;
+        } else {
+            changed.push(CourseChange {
+                code: course.code.clone(),
+                changes,
+            });
+        }
+    }
+
+    let removed = current
+        .iter()
+        .filter(|course| !incoming_codes.contains(course.code.as_str()))
+        .map(course_record)
+        .collect();
+
+    CatalogImportReport {
+        dry_run,
+        applied: false,
+        added,
+        changed,
+        removed,
+        unchanged,
+        invalid,
+    }
+}
+
+/// Imports a catalog file. Nothing is written on a dry run or when any row is invalid.
+pub async fn import_catalog(
+    db_wrapper: &DBPoolWrapper,
+    data: &[u8],
+    format: CatalogFormat,
+    dry_run: bool,
+    remove_missing: bool,
+) -> Result<CatalogImportReport, CatalogError> {
+    let (records, invalid) = parse_catalog(data, format)?;
+    let current = get_all_courses(db_wrapper)
+        .await
+        .map_err(CatalogError::Database)?;
+
+    let mut report = diff_catalog(&current, &records, invalid, dry_run);
+    if dry_run || !report.invalid.is_empty() {
+        return Ok(report);
+    }
+
+    let upserts: Vec<CourseRecord> = report
+        .added
+        .iter()
+        .cloned()
+        .chain(
+            records
+                .iter()
+                .filter(|record| report.changed.iter().any(|c| c.code == record.code))
+                .cloned(),
+        )
+        .collect();
+    let removed_codes: Vec<String> = if remove_missing {
+        report.removed.iter().map(|c| c.code.clone()).collect()
+    } else {
+        Vec::new()
+    };
+
+    apply_course_import(db_wrapper, &upserts, &removed_codes)
+        .await
+        .map_err(CatalogError::Database)?;
+    report.applied = true;
+
+    Ok(report)
+}
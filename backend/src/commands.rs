@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+use crate::catalog::{import_catalog, CatalogFormat};
+use crate::db::DBPoolWrapper;
+use crate::env::EnvVars;
+use crate::fsck::check_storage;
+use crate::pdf_worker::run_worker;
+use crate::preview::backfill_previews;
+
+#[derive(Subcommand, Clone)]
+/// One-off maintenance tasks. The server is started when no command is given.
+pub enum Command {
+    /// Import the institute course catalog from a CSV or JSON file
+    ImportCourses {
+        /// Path to the catalog file, the format is picked from the extension
+        path: PathBuf,
+        #[arg(long)]
+        /// Only report what would change, without writing anything
+        dry_run: bool,
+        #[arg(long)]
+        /// Delete catalog courses that are missing from the file
+        remove_missing: bool,
+    },
+    /// Render previews again for notes without one, or whose preview files are missing
+    BackfillPreviews {
+        #[arg(long, default_value = "4")]
+        /// Number of notes rendered at the same time
+        concurrency: usize,
+        #[arg(long)]
+        /// Only list the notes missing previews, without rendering anything
+        dry_run: bool,
+    },
+    /// Check stored files against the database: orphaned files, missing PDFs and wrong preview flags
+    Fsck {
+        #[arg(long)]
+        /// Move orphaned files to the quarantine directory and correct preview flags
+        fix: bool,
+    },
+    /// Run one pdfium task read from stdin, used by the server to isolate pdfium
+    #[command(hide = true)]
+    PdfWorker,
+}
+
+/// Runs a maintenance command to completion.
+pub async fn run(
+    command: Command,
+    env_vars: &EnvVars,
+    db_wrapper: &DBPoolWrapper,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::ImportCourses {
+            path,
+            dry_run,
+            remove_missing,
+        } => {
+            let format = CatalogFormat::from_path(&path)
+                .ok_or("Course catalog must be a .csv or .json file")?;
+            let data = tokio::fs::read(&path).await?;
+
+            let report = import_catalog(db_wrapper, &data, format, dry_run, remove_missing).await?;
+            print!("{}", report.to_diff());
+
+            if !report.invalid.is_empty() {
+                return Err("Catalog has invalid rows, nothing was imported".into());
+            }
+            if report.applied {
+                tracing::info!("Course catalog imported from {}", path.display());
+            }
+        }
+        Command::BackfillPreviews { concurrency, dry_run } => {
+            let report = backfill_previews(db_wrapper, &env_vars.storage, &env_vars.paths, concurrency, dry_run).await?;
+            println!("{} of {} notes are missing previews", report.missing.len(), report.checked);
+            if dry_run {
+                for note_id in &report.missing {
+                    println!("  {}", note_id);
+                }
+                return Ok(());
+            }
+
+            println!("Regenerated {} previews", report.regenerated);
+            for failure in &report.failed {
+                println!("  {} failed: {}", failure.note_id, failure.error);
+            }
+            if !report.failed.is_empty() {
+                return Err(format!("Failed to regenerate {} previews", report.failed.len()).into());
+            }
+        }
+        Command::Fsck { fix } => {
+            let report = check_storage(db_wrapper, &env_vars.storage, &env_vars.paths, fix).await?;
+            println!("Checked {} notes and {} files", report.checked_notes, report.checked_files);
+
+            println!("{} orphaned files", report.orphaned_files.len());
+            for key in &report.orphaned_files {
+                println!("  {}", key);
+            }
+            println!("{} notes with a missing PDF", report.missing_pdfs.len());
+            for note_id in &report.missing_pdfs {
+                println!("  {}", note_id);
+            }
+            println!("{} notes with a wrong preview flag", report.preview_mismatches.len());
+            for mismatch in &report.preview_mismatches {
+                println!(
+                    "  {}: has_preview_image = {} with {} pages, found {} complete pages",
+                    mismatch.note_id, mismatch.has_preview_image, mismatch.preview_pages, mismatch.found_pages
+                );
+            }
+
+            if !fix {
+                if !report.is_clean() {
+                    return Err("Storage is inconsistent with the database".into());
+                }
+                return Ok(());
+            }
+
+            println!(
+                "Quarantined {} files and corrected {} preview flags",
+                report.quarantined, report.corrected
+            );
+            if !report.missing_pdfs.is_empty() {
+                println!("Notes with a missing PDF are left as they are, restore their files or remove them");
+            }
+            for failure in &report.failed {
+                println!("  {}", failure);
+            }
+            if !report.failed.is_empty() {
+                return Err(format!("Failed to fix {} problems", report.failed.len()).into());
+            }
+        }
+        Command::PdfWorker => run_worker(env_vars.pdfium_library_path.as_deref())?,
+    }
+
+    Ok(())
+}
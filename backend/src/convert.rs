@@ -0,0 +1,323 @@
+//! Conversion of uploaded images and office documents to PDF.
+//!
+//! Notes are always stored as a single PDF. Photos of notes (JPEG, PNG or HEIC) are merged into
+//! one PDF with a page per image, in upload order, and DOCX/PPTX files are converted with a local
+//! headless LibreOffice. File types are detected from their contents, not the client's claims.
+
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use pdfium_render::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::pdf::{pdfium, PdfError};
+use crate::pdf_worker::{acquire_worker_slot, run_pdf_task, PdfTask};
+use crate::upload::{temp_file_path, TempUpload};
+
+/// Most images that can be merged into a single note
+pub const MAX_IMAGES: usize = 50;
+/// Images are scaled down to at most this many pixels on their longer side, about A4 at 300 DPI
+const MAX_IMAGE_DIMENSION: u32 = 3508;
+/// Width of the pages images are placed on, in points. Page heights follow the image's aspect ratio.
+const IMAGE_PAGE_WIDTH: f32 = 595.0;
+/// How much of the end of a ZIP file is searched for the entries identifying office documents
+const ZIP_DIRECTORY_SEARCH_SIZE: u64 = 256 * 1024;
+const HEIF_CONVERT_TIMEOUT: Duration = Duration::from_secs(60);
+const LIBREOFFICE_TIMEOUT: Duration = Duration::from_secs(120);
+const IMAGES_TO_PDF_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Pdf,
+    Jpeg,
+    Png,
+    Heic,
+    Docx,
+    Pptx,
+}
+
+impl FileKind {
+    pub fn is_image(&self) -> bool {
+        matches!(self, FileKind::Jpeg | FileKind::Png | FileKind::Heic)
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            FileKind::Pdf => "pdf",
+            FileKind::Jpeg => "jpg",
+            FileKind::Png => "png",
+            FileKind::Heic => "heic",
+            FileKind::Docx => "docx",
+            FileKind::Pptx => "pptx",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The files are of a type that isn't supported, or can't be combined
+    Unsupported(String),
+    /// The file couldn't be converted, most likely because it is damaged
+    Failed(String),
+    /// A tool needed for the conversion isn't available
+    Unavailable(String),
+    /// Too many files are being converted to start another
+    Busy,
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConvertError::Unsupported(msg) => write!(f, "Unsupported upload: {}", msg),
+            ConvertError::Failed(msg) => write!(f, "Failed to convert upload to PDF: {}", msg),
+            ConvertError::Unavailable(msg) => write!(f, "Conversion to PDF is unavailable: {}", msg),
+            ConvertError::Busy => write!(f, "Too many files are being converted to PDF"),
+            ConvertError::Io(err) => write!(f, "Failed to convert upload to PDF: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<std::io::Error> for ConvertError {
+    fn from(err: std::io::Error) -> Self {
+        ConvertError::Io(err)
+    }
+}
+
+impl From<PdfError> for ConvertError {
+    fn from(err: PdfError) -> Self {
+        match err {
+            PdfError::Unavailable => ConvertError::Unavailable(err.to_string()),
+            PdfError::Busy => ConvertError::Busy,
+            PdfError::Worker(msg) => ConvertError::Failed(msg),
+            PdfError::Io(err) => ConvertError::Io(err),
+            err => ConvertError::Failed(err.to_string()),
+        }
+    }
+}
+
+/// Detects the type of an uploaded file from its contents.
+pub async fn detect_file_kind(path: &Path) -> Result<Option<FileKind>, std::io::Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut header = Vec::with_capacity(16);
+    (&mut file).take(16).read_to_end(&mut header).await?;
+
+    let kind = if header.starts_with(b"%PDF-") {
+        Some(FileKind::Pdf)
+    } else if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(FileKind::Jpeg)
+    } else if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(FileKind::Png)
+    } else if header.len() >= 12
+        && &header[4..8] == b"ftyp"
+        && matches!(&header[8..12], b"heic" | b"heix" | b"hevc" | b"hevx" | b"mif1" | b"msf1")
+    {
+        Some(FileKind::Heic)
+    } else if header.starts_with(b"PK\x03\x04") {
+        // DOCX and PPTX are ZIP archives, told apart by the entries in the central directory at the end
+        let size = file.metadata().await?.len();
+        file.seek(std::io::SeekFrom::Start(size.saturating_sub(ZIP_DIRECTORY_SEARCH_SIZE)))
+            .await?;
+        let mut directory = Vec::new();
+        file.read_to_end(&mut directory).await?;
+
+        let contains = |name: &[u8]| directory.windows(name.len()).any(|window| window == name);
+        if contains(b"word/document.xml") {
+            Some(FileKind::Docx)
+        } else if contains(b"ppt/presentation.xml") {
+            Some(FileKind::Pptx)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    Ok(kind)
+}
+
+/// Runs a conversion tool, killing it if it takes longer than `timeout`. Tools like LibreOffice
+/// need hundreds of MiB each, so they share the pdfium workers' slots.
+async fn run_tool(command: &mut Command, timeout: Duration) -> Result<(), ConvertError> {
+    let _slot = acquire_worker_slot().await?;
+    let program = command.as_std().get_program().to_string_lossy().into_owned();
+    let output = tokio::time::timeout(timeout, command.kill_on_drop(true).output())
+        .await
+        .map_err(|_| ConvertError::Failed(format!("{} timed out", program)))?
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => ConvertError::Unavailable(format!("{} is not installed", program)),
+            _ => ConvertError::Io(err),
+        })?;
+
+    if !output.status.success() {
+        return Err(ConvertError::Failed(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+
+    Ok(())
+}
+
+fn load_image(path: &Path) -> Result<DynamicImage, ConvertError> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()
+        .map_err(|err| ConvertError::Failed(err.to_string()))?;
+    // Phone cameras store rotation separately from the pixels
+    let orientation = decoder
+        .orientation()
+        .map_err(|err| ConvertError::Failed(err.to_string()))?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|err| ConvertError::Failed(err.to_string()))?;
+    image.apply_orientation(orientation);
+
+    if image.width().max(image.height()) > MAX_IMAGE_DIMENSION {
+        image = image.resize(MAX_IMAGE_DIMENSION, MAX_IMAGE_DIMENSION, image::imageops::FilterType::Lanczos3);
+    }
+
+    Ok(image)
+}
+
+/// Merges images into a PDF. This runs in a pdfium worker process.
+pub(crate) fn write_images_pdf(images: &[PathBuf], output: &Path) -> Result<(), ConvertError> {
+    let pdfium = pdfium()?;
+    let mut document = pdfium
+        .create_new_pdf()
+        .map_err(|err| ConvertError::Failed(err.to_string()))?;
+
+    for path in images {
+        let image = load_image(path)?;
+        let width = PdfPoints::new(IMAGE_PAGE_WIDTH);
+        let height = PdfPoints::new(IMAGE_PAGE_WIDTH * image.height() as f32 / image.width() as f32);
+
+        let object = PdfPageImageObject::new_with_size(&document, &image, width, height)
+            .map_err(|err| ConvertError::Failed(err.to_string()))?;
+        document
+            .pages_mut()
+            .create_page_at_end(PdfPagePaperSize::Custom(width, height))
+            .and_then(|mut page| page.objects_mut().add_image_object(object).map(|_| ()))
+            .map_err(|err| ConvertError::Failed(err.to_string()))?;
+    }
+
+    document
+        .save_to_file(output)
+        .map_err(|err| ConvertError::Failed(err.to_string()))
+}
+
+/// Merges images into a PDF with one page per image, in the given order.
+async fn images_to_pdf(images: &[(TempUpload, FileKind)], dir: &Path) -> Result<TempUpload, ConvertError> {
+    let work_dir = dir.join(format!(".convert-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+
+    let result = async {
+        let mut paths = Vec::with_capacity(images.len());
+        for (index, (image, kind)) in images.iter().enumerate() {
+            if *kind == FileKind::Heic {
+                let converted = work_dir.join(format!("{}.jpg", index));
+                run_tool(
+                    Command::new("heif-convert").arg(image.path()).arg(&converted),
+                    HEIF_CONVERT_TIMEOUT,
+                )
+                .await?;
+                paths.push(converted);
+            } else {
+                paths.push(image.path().to_path_buf());
+            }
+        }
+
+        let output = temp_file_path(dir);
+        let task = PdfTask::ImagesToPdf {
+            images: paths,
+            output: output.clone(),
+        };
+        if let Err(err) = run_pdf_task::<()>(task, IMAGES_TO_PDF_TIMEOUT).await {
+            let _ = tokio::fs::remove_file(&output).await;
+            return Err(err.into());
+        }
+
+        Ok(TempUpload::from_file(output).await?)
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+/// Converts a DOCX or PPTX file to PDF with a headless LibreOffice.
+async fn office_to_pdf(document: &TempUpload, kind: FileKind, dir: &Path) -> Result<TempUpload, ConvertError> {
+    let work_dir = dir.join(format!(".convert-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+
+    let result = async {
+        // LibreOffice picks the import filter by extension
+        let input = work_dir.join(format!("document.{}", kind.extension()));
+        tokio::fs::copy(document.path(), &input).await?;
+
+        // A separate profile per conversion, since LibreOffice won't run twice on the same one
+        let profile = format!("-env:UserInstallation=file://{}", work_dir.join("profile").display());
+        run_tool(
+            Command::new("soffice")
+                .arg(profile)
+                .args(["--headless", "--norestore", "--convert-to", "pdf", "--outdir"])
+                .arg(&work_dir)
+                .arg(&input),
+            LIBREOFFICE_TIMEOUT,
+        )
+        .await?;
+
+        let output = temp_file_path(dir);
+        tokio::fs::rename(work_dir.join("document.pdf"), &output)
+            .await
+            .map_err(|_| ConvertError::Failed("LibreOffice did not produce a PDF".to_string()))?;
+
+        Ok(TempUpload::from_file(output).await?)
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+/// Turns the uploaded files into the single PDF stored for a note. A single PDF is returned as is.
+///
+/// Converted PDFs are written to `dir`, which must be on the same filesystem as the notes.
+pub async fn convert_to_pdf(files: Vec<TempUpload>, dir: &Path) -> Result<TempUpload, ConvertError> {
+    let mut detected = Vec::with_capacity(files.len());
+    for file in files {
+        let kind = detect_file_kind(file.path()).await?.ok_or_else(|| {
+            ConvertError::Unsupported(
+                "Only PDF, JPEG, PNG, HEIC, DOCX and PPTX files are supported".to_string(),
+            )
+        })?;
+        detected.push((file, kind));
+    }
+
+    if detected.iter().all(|(_, kind)| kind.is_image()) {
+        if detected.len() > MAX_IMAGES {
+            return Err(ConvertError::Unsupported(format!(
+                "At most {} images can be uploaded at once",
+                MAX_IMAGES
+            )));
+        }
+        if !detected.is_empty() {
+            return images_to_pdf(&detected, dir).await;
+        }
+    }
+
+    if detected.len() != 1 {
+        return Err(ConvertError::Unsupported(
+            "Upload either a single document or one or more images".to_string(),
+        ));
+    }
+
+    let (file, kind) = detected.remove(0);
+    if kind == FileKind::Pdf {
+        return Ok(file);
+    }
+    office_to_pdf(&file, kind, dir).await
+}
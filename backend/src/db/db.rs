@@ -1,5 +1,4 @@
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
-use std::env;
 use crate::env::EnvVars;
 
 #[derive(Clone)]
@@ -0,0 +1,58 @@
+use crate::api::models::{AuditEventFilter, CreateAuditEvent};
+use crate::db::db::DBPoolWrapper;
+use crate::db::models::AuditEvent;
+
+/// Inserts a new audit event into the database.
+pub async fn insert_audit_event(
+    db_wrapper: &DBPoolWrapper,
+    event: CreateAuditEvent,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_events (actor_user_id, action, target_type, target_id, ip_address, user_agent, diff)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        event.actor_user_id,
+        event.action,
+        event.target_type,
+        event.target_id,
+        event.ip_address,
+        event.user_agent,
+        event.diff
+    )
+        .execute(db_wrapper.pool())
+        .await?;
+
+    Ok(())
+}
+
+/// Fetches audit events matching the filter, newest first.
+pub async fn get_audit_events(
+    db_wrapper: &DBPoolWrapper,
+    filter: AuditEventFilter,
+) -> Result<Vec<AuditEvent>, sqlx::Error> {
+    let events = sqlx::query_as!(
+        AuditEvent,
+        r#"
+        SELECT id, actor_user_id, action, target_type, target_id, ip_address, user_agent, diff, created_at
+        FROM audit_events
+        WHERE ($1::uuid IS NULL OR actor_user_id = $1)
+          AND ($2::uuid IS NULL OR target_id = $2)
+          AND ($3::text IS NULL OR action = $3)
+          AND ($4::timestamptz IS NULL OR created_at >= $4)
+          AND ($5::timestamptz IS NULL OR created_at < $5)
+        ORDER BY created_at DESC
+        LIMIT $6
+        "#,
+        filter.actor_user_id,
+        filter.target_id,
+        filter.action,
+        filter.from,
+        filter.to,
+        filter.limit as i64
+    )
+        .fetch_all(db_wrapper.pool())
+        .await?;
+
+    Ok(events)
+}
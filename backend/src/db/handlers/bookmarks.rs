@@ -0,0 +1,113 @@
+use crate::db::models::{ContentType, DBBookmark, NoteWithUser, Semester};
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+/// Bookmarks a note for a user. Bookmarking an already bookmarked note returns the existing bookmark.
+pub async fn add_bookmark(
+    db_wrapper: &DBPoolWrapper,
+    user_id: Uuid,
+    note_id: Uuid,
+) -> Result<DBBookmark, sqlx::Error> {
+    let bookmark = sqlx::query_as!(
+        DBBookmark,
+        r#"
+        INSERT INTO bookmarks (user_id, note_id) VALUES ($1, $2)
+        ON CONFLICT (user_id, note_id) DO UPDATE SET user_id = EXCLUDED.user_id
+        RETURNING *
+        "#,
+        user_id,
+        note_id
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(bookmark)
+}
+
+/// Removes a user's bookmark on a note. Returns whether a bookmark existed.
+pub async fn remove_bookmark(
+    db_wrapper: &DBPoolWrapper,
+    user_id: Uuid,
+    note_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM bookmarks WHERE user_id = $1 AND note_id = $2",
+        user_id,
+        note_id
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Lists the notes bookmarked by a user, most recently bookmarked first.
+pub async fn get_bookmarked_notes(
+    db_wrapper: &DBPoolWrapper,
+    user_id: Uuid,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            TRUE as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            bookmarks b
+        JOIN
+            notes n ON b.note_id = n.id
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $1
+        WHERE b.user_id = $1
+        ORDER BY
+            b.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(notes)
+}
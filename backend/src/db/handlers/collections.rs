@@ -0,0 +1,383 @@
+use crate::api::models::{CollectionFilter, CreateCollection, UpdateCollection};
+use crate::db::models::{
+    CollectionWithOwner, ContentType, DBCollection, DBCollectionVote, NoteWithUser, Semester, VoteType,
+};
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+/// Inserts a new collection into the database.
+pub async fn create_collection(
+    db_wrapper: &DBPoolWrapper,
+    new_collection: CreateCollection,
+) -> Result<DBCollection, sqlx::Error> {
+    let collection = sqlx::query_as!(
+        DBCollection,
+        r#"
+        INSERT INTO collections (owner_user_id, name, description, is_public)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+        new_collection.owner_user_id,
+        new_collection.name,
+        new_collection.description,
+        new_collection.is_public
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(collection)
+}
+
+/// Fetches a single collection row without any visibility checks.
+pub async fn get_collection(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+) -> Result<Option<DBCollection>, sqlx::Error> {
+    let collection = sqlx::query_as!(
+        DBCollection,
+        "SELECT * FROM collections WHERE id = $1",
+        collection_id
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(collection)
+}
+
+/// Fetches collections matching the filter along with their owner and vote counts.
+///
+/// Private collections are only returned to their owner, or when looked up by share token.
+pub async fn get_collections(
+    db_wrapper: &DBPoolWrapper,
+    filter: CollectionFilter,
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<CollectionWithOwner>, sqlx::Error> {
+    let search_term = filter.search_term.map(|query| format!("%{}%", query));
+    let collections = sqlx::query_as!(
+        CollectionWithOwner,
+        r#"
+        SELECT
+            c.id as "collection_id!",
+            c.name as "collection_name!",
+            c.description as "collection_description",
+            c.is_public as "collection_is_public!",
+            c.share_token as "collection_share_token!",
+            c.owner_user_id as "collection_owner_user_id!",
+            c.created_at as "collection_created_at!",
+            c.updated_at as "collection_updated_at!",
+            COALESCE(note_counts.count, 0) as "collection_note_count!",
+            COALESCE(upvote_counts.count, 0) as "collection_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "collection_downvote_count!",
+            user_vote.is_upvote as "collection_user_upvote?",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            collections c
+        JOIN
+            users u ON c.owner_user_id = u.id
+        LEFT JOIN
+            (SELECT collection_id, COUNT(*) as count
+             FROM collection_notes
+             GROUP BY collection_id) note_counts ON c.id = note_counts.collection_id
+        LEFT JOIN
+            (SELECT collection_id, COUNT(*) as count
+             FROM collection_votes
+             WHERE is_upvote = true
+             GROUP BY collection_id) upvote_counts ON c.id = upvote_counts.collection_id
+        LEFT JOIN
+            (SELECT collection_id, COUNT(*) as count
+             FROM collection_votes
+             WHERE is_upvote = false
+             GROUP BY collection_id) downvote_counts ON c.id = downvote_counts.collection_id
+        LEFT JOIN
+            collection_votes user_vote ON c.id = user_vote.collection_id AND user_vote.user_id = $1
+        WHERE ($2::uuid IS NULL OR c.id = $2)
+          AND ($3::uuid IS NULL OR c.share_token = $3)
+          AND ($4::uuid IS NULL OR c.owner_user_id = $4)
+          AND ($5::text IS NULL OR c.name ILIKE $5 OR c.description ILIKE $5)
+          AND (c.is_public OR c.owner_user_id = $1 OR $3::uuid IS NOT NULL)
+        ORDER BY
+            COALESCE(upvote_counts.count, 0) DESC, c.updated_at DESC
+        LIMIT $6
+        "#,
+        current_user_id.as_ref(),
+        filter.collection_id,
+        filter.share_token,
+        filter.owner_user_id,
+        search_term,
+        filter.limit.map(|limit| limit as i64)
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(collections)
+}
+
+/// Updates the given fields of a collection, leaving the others unchanged.
+pub async fn update_collection(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+    update: UpdateCollection,
+) -> Result<DBCollection, sqlx::Error> {
+    let set_description = update.description.is_some();
+    let collection = sqlx::query_as!(
+        DBCollection,
+        r#"
+        UPDATE collections
+        SET name = COALESCE($2, name),
+            description = CASE WHEN $5 THEN $3 ELSE description END,
+            is_public = COALESCE($4, is_public),
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        collection_id,
+        update.name,
+        update.description.flatten(),
+        update.is_public,
+        set_description
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(collection)
+}
+
+pub async fn delete_collection(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM collections WHERE id = $1", collection_id)
+        .execute(db_wrapper.pool())
+        .await?;
+
+    Ok(())
+}
+
+/// Appends a note to the end of a collection. Adding a note that is already present is a no-op.
+pub async fn add_note_to_collection(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+    note_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO collection_notes (collection_id, note_id, position)
+        SELECT $1, $2, COALESCE(MAX(position) + 1, 0)
+        FROM collection_notes
+        WHERE collection_id = $1
+        ON CONFLICT (collection_id, note_id) DO NOTHING
+        "#,
+        collection_id,
+        note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE collections SET updated_at = NOW() WHERE id = $1",
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Removes a note from a collection. Returns whether the note was in the collection.
+pub async fn remove_note_from_collection(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+    note_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM collection_notes WHERE collection_id = $1 AND note_id = $2",
+        collection_id,
+        note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE collections SET updated_at = NOW() WHERE id = $1",
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Reorders the notes of a collection to match `note_ids`.
+///
+/// Returns `false` without changing anything if `note_ids` is not exactly the set of notes in the collection.
+pub async fn reorder_collection_notes(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+    note_ids: &[Uuid],
+) -> Result<bool, sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    let current_note_ids = sqlx::query_scalar!(
+        "SELECT note_id FROM collection_notes WHERE collection_id = $1 FOR UPDATE",
+        collection_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut current_sorted = current_note_ids;
+    current_sorted.sort();
+    let mut requested_sorted = note_ids.to_vec();
+    requested_sorted.sort();
+    if current_sorted != requested_sorted {
+        tx.rollback().await?;
+        return Ok(false);
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE collection_notes cn
+        SET position = ordering.position - 1
+        FROM UNNEST($2::uuid[]) WITH ORDINALITY AS ordering(note_id, position)
+        WHERE cn.collection_id = $1 AND cn.note_id = ordering.note_id
+        "#,
+        collection_id,
+        note_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE collections SET updated_at = NOW() WHERE id = $1",
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Lists the notes of a collection in the collection's order.
+pub async fn get_collection_notes(
+    db_wrapper: &DBPoolWrapper,
+    collection_id: Uuid,
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            collection_notes cn
+        JOIN
+            notes n ON cn.note_id = n.id
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE cn.collection_id = $1
+        ORDER BY
+            cn.position ASC
+        "#,
+        collection_id,
+        current_user_id.as_ref()
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(notes)
+}
+
+pub async fn vote_collection(
+    db_wrapper: &DBPoolWrapper,
+    user_id: Uuid,
+    collection_id: Uuid,
+    vote_type: VoteType,
+) -> Result<Option<DBCollectionVote>, sqlx::Error> {
+    match vote_type {
+        VoteType::Upvote(is_upvote) => {
+            let vote = sqlx::query_as!(
+                DBCollectionVote,
+                r#"
+                INSERT INTO collection_votes (user_id, collection_id, is_upvote) VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, collection_id) DO UPDATE SET is_upvote = EXCLUDED.is_upvote
+                RETURNING *
+                "#,
+                user_id,
+                collection_id,
+                is_upvote
+            )
+            .fetch_one(db_wrapper.pool())
+            .await?;
+            Ok(Some(vote))
+        }
+        VoteType::Remove => {
+            sqlx::query!(
+                "DELETE FROM collection_votes WHERE user_id = $1 AND collection_id = $2",
+                user_id,
+                collection_id
+            )
+            .execute(db_wrapper.pool())
+            .await?;
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::api::models::CreateComment;
+use crate::db::models::{CommentWithAuthor, DBComment};
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+/// Inserts a new comment into the database.
+pub async fn create_comment(
+    db_wrapper: &DBPoolWrapper,
+    new_comment: CreateComment,
+) -> Result<DBComment, sqlx::Error> {
+    let comment = sqlx::query_as!(
+        DBComment,
+        r#"
+        INSERT INTO comments (note_id, author_user_id, parent_comment_id, body)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+        new_comment.note_id,
+        new_comment.author_user_id,
+        new_comment.parent_comment_id,
+        new_comment.body
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(comment)
+}
+
+pub async fn get_comment(
+    db_wrapper: &DBPoolWrapper,
+    comment_id: Uuid,
+) -> Result<Option<DBComment>, sqlx::Error> {
+    let comment = sqlx::query_as!(
+        DBComment,
+        "SELECT * FROM comments WHERE id = $1",
+        comment_id
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(comment)
+}
+
+/// Lists all comments on a note with their authors, oldest first.
+pub async fn get_note_comments(
+    db_wrapper: &DBPoolWrapper,
+    note_id: Uuid,
+) -> Result<Vec<CommentWithAuthor>, sqlx::Error> {
+    let comments = sqlx::query_as!(
+        CommentWithAuthor,
+        r#"
+        SELECT
+            c.id as "comment_id!",
+            c.note_id as "comment_note_id!",
+            c.parent_comment_id as "comment_parent_comment_id",
+            c.body as "comment_body!",
+            c.created_at as "comment_created_at!",
+            c.edited_at as "comment_edited_at",
+            c.deleted_at as "comment_deleted_at",
+            c.removed_by_user_id as "comment_removed_by_user_id",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            comments c
+        JOIN
+            users u ON c.author_user_id = u.id
+        WHERE c.note_id = $1
+        ORDER BY
+            c.created_at ASC
+        "#,
+        note_id
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(comments)
+}
+
+pub async fn update_comment_body(
+    db_wrapper: &DBPoolWrapper,
+    comment_id: Uuid,
+    body: &str,
+) -> Result<DBComment, sqlx::Error> {
+    let comment = sqlx::query_as!(
+        DBComment,
+        "UPDATE comments SET body = $2, edited_at = NOW() WHERE id = $1 RETURNING *",
+        comment_id,
+        body
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(comment)
+}
+
+/// Soft-deletes a comment so its replies stay in place. `removed_by` is set for moderator removals.
+pub async fn delete_comment(
+    db_wrapper: &DBPoolWrapper,
+    comment_id: Uuid,
+    removed_by: Option<Uuid>,
+) -> Result<DBComment, sqlx::Error> {
+    let comment = sqlx::query_as!(
+        DBComment,
+        r#"
+        UPDATE comments SET deleted_at = NOW(), removed_by_user_id = $2
+        WHERE id = $1
+        RETURNING *
+        "#,
+        comment_id,
+        removed_by
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(comment)
+}
@@ -0,0 +1,225 @@
+use crate::db::models::{ContentType, CourseRecord, CourseWithNoteCount, DBCourse, NoteWithUser, Semester};
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+pub async fn get_course_by_code(
+    db_wrapper: &DBPoolWrapper,
+    code: &str,
+) -> Result<Option<DBCourse>, sqlx::Error> {
+    let course = sqlx::query_as!(
+        DBCourse,
+        "SELECT * FROM courses WHERE code = $1",
+        code
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(course)
+}
+
+/// Lists courses with the number of notes of each.
+///
+/// Besides the catalog, this includes the codes of notes that aren't linked to a catalog course,
+/// named after the most common name their notes use. `query` matches course codes by prefix and
+/// course names anywhere, for autocompletion.
+pub async fn get_courses(
+    db_wrapper: &DBPoolWrapper,
+    query: Option<&str>,
+    department: Option<&str>,
+    num_courses: usize,
+) -> Result<Vec<CourseWithNoteCount>, sqlx::Error> {
+    let code_prefix = query.map(|q| format!("{}%", q));
+    let name_term = query.map(|q| format!("%{}%", q));
+    let courses = sqlx::query_as!(
+        CourseWithNoteCount,
+        r#"
+        SELECT
+            id as "id?",
+            code as "code!",
+            name as "name!",
+            department,
+            credits,
+            note_count as "note_count!"
+        FROM
+            (SELECT
+                c.id,
+                c.code::text,
+                c.name::text,
+                c.department::text,
+                c.credits,
+                COUNT(n.id) as note_count
+            FROM
+                courses c
+            LEFT JOIN
+                notes n ON n.course_id = c.id
+            WHERE ($1::text IS NULL OR c.code ILIKE $1 OR c.name ILIKE $2)
+              AND ($3::text IS NULL OR c.department = $3)
+            GROUP BY
+                c.id
+            UNION ALL
+            SELECT
+                NULL::uuid,
+                n.course_code::text,
+                MODE() WITHIN GROUP (ORDER BY n.course_name)::text,
+                NULL::text,
+                NULL::int,
+                COUNT(*)
+            FROM
+                notes n
+            WHERE n.course_id IS NULL
+              AND ($1::text IS NULL OR n.course_code ILIKE $1 OR n.course_name ILIKE $2)
+              AND $3::text IS NULL
+            GROUP BY
+                n.course_code) courses
+        ORDER BY
+            code ASC
+        LIMIT $4
+        "#,
+        code_prefix,
+        name_term,
+        department,
+        num_courses as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(courses)
+}
+
+/// Lists the notes of a course by its normalized code, newest first. Notes of catalog courses
+/// always use the catalog's code, so this works for courses both in and outside the catalog.
+pub async fn get_course_notes(
+    db_wrapper: &DBPoolWrapper,
+    course_code: &str,
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            notes n
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE n.course_code = $1
+        ORDER BY
+            n.created_at DESC
+        "#,
+        course_code,
+        current_user_id.as_ref()
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(notes)
+}
+
+pub async fn get_all_courses(db_wrapper: &DBPoolWrapper) -> Result<Vec<DBCourse>, sqlx::Error> {
+    let courses = sqlx::query_as!(DBCourse, "SELECT * FROM courses ORDER BY code")
+        .fetch_all(db_wrapper.pool())
+        .await?;
+
+    Ok(courses)
+}
+
+/// Upserts catalog courses by code and deletes the courses in `removed_codes`, in one transaction.
+///
+/// Notes are relinked afterwards so that they pick up new courses and renamed course names.
+pub async fn apply_course_import(
+    db_wrapper: &DBPoolWrapper,
+    upserts: &[CourseRecord],
+    removed_codes: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    let codes: Vec<String> = upserts.iter().map(|c| c.code.clone()).collect();
+    let names: Vec<String> = upserts.iter().map(|c| c.name.clone()).collect();
+    let departments: Vec<Option<String>> = upserts.iter().map(|c| c.department.clone()).collect();
+    let credits: Vec<Option<i32>> = upserts.iter().map(|c| c.credits).collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO courses (code, name, department, credits)
+        SELECT * FROM UNNEST($1::varchar[], $2::varchar[], $3::varchar[], $4::int[])
+        ON CONFLICT (code) DO UPDATE
+        SET name = EXCLUDED.name,
+            department = EXCLUDED.department,
+            credits = EXCLUDED.credits
+        "#,
+        &codes,
+        &names,
+        &departments as &[Option<String>],
+        &credits as &[Option<i32>]
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM courses WHERE code = ANY($1)",
+        removed_codes
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE notes n
+        SET course_id = c.id, course_name = c.name
+        FROM courses c
+        WHERE n.course_code = c.code
+          AND (n.course_id IS DISTINCT FROM c.id OR n.course_name <> c.name)
+        "#
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
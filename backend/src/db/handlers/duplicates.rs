@@ -0,0 +1,241 @@
+use crate::db::models::DBNoteDuplicate;
+use crate::db::DBPoolWrapper;
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Finds the note with the given file hash, if any.
+pub async fn get_note_id_by_file_hash(
+    db_wrapper: &DBPoolWrapper,
+    file_sha256: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let note_id = sqlx::query_scalar!("SELECT id FROM notes WHERE file_sha256 = $1", file_sha256)
+        .fetch_optional(db_wrapper.pool())
+        .await?;
+
+    Ok(note_id)
+}
+
+pub async fn set_note_file_sha256(
+    db_wrapper: &DBPoolWrapper,
+    note_id: Uuid,
+    file_sha256: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE notes SET file_sha256 = $1 WHERE id = $2",
+        file_sha256,
+        note_id
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn set_note_text_sketch(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    text_sketch: &[i64],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE notes SET text_sketch = $1 WHERE id = $2",
+        text_sketch,
+        note_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Fetches the sketches of other notes sharing at least one shingle hash with `text_sketch`.
+pub async fn get_sketch_candidates(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    text_sketch: &[i64],
+) -> Result<Vec<(Uuid, Vec<i64>)>, sqlx::Error> {
+    let candidates = sqlx::query!(
+        r#"
+        SELECT id, text_sketch as "text_sketch!"
+        FROM notes
+        WHERE id <> $1 AND text_sketch && $2
+        "#,
+        note_id,
+        text_sketch
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(candidates
+        .into_iter()
+        .map(|candidate| (candidate.id, candidate.text_sketch))
+        .collect())
+}
+
+pub async fn insert_note_duplicate(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    duplicate_of_note_id: Uuid,
+    similarity: f32,
+) -> Result<DBNoteDuplicate, sqlx::Error> {
+    let duplicate = sqlx::query_as!(
+        DBNoteDuplicate,
+        r#"
+        INSERT INTO note_duplicates (note_id, duplicate_of_note_id, similarity) VALUES ($1, $2, $3)
+        ON CONFLICT (note_id, duplicate_of_note_id) DO UPDATE SET similarity = EXCLUDED.similarity
+        RETURNING *
+        "#,
+        note_id,
+        duplicate_of_note_id,
+        similarity
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(duplicate)
+}
+
+pub async fn get_note_duplicate(
+    db_wrapper: &DBPoolWrapper,
+    duplicate_id: Uuid,
+) -> Result<Option<DBNoteDuplicate>, sqlx::Error> {
+    let duplicate = sqlx::query_as!(
+        DBNoteDuplicate,
+        "SELECT * FROM note_duplicates WHERE id = $1",
+        duplicate_id
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(duplicate)
+}
+
+/// Lists near-duplicate pairs, most similar first. Dismissed pairs are only included on request.
+pub async fn get_note_duplicates(
+    db_wrapper: &DBPoolWrapper,
+    include_dismissed: bool,
+    num_duplicates: usize,
+) -> Result<Vec<DBNoteDuplicate>, sqlx::Error> {
+    let duplicates = sqlx::query_as!(
+        DBNoteDuplicate,
+        r#"
+        SELECT * FROM note_duplicates
+        WHERE $1 OR dismissed_at IS NULL
+        ORDER BY similarity DESC, created_at DESC
+        LIMIT $2
+        "#,
+        include_dismissed,
+        num_duplicates as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(duplicates)
+}
+
+pub async fn dismiss_note_duplicate(
+    db_wrapper: &DBPoolWrapper,
+    duplicate_id: Uuid,
+    user_id: Uuid,
+) -> Result<DBNoteDuplicate, sqlx::Error> {
+    let duplicate = sqlx::query_as!(
+        DBNoteDuplicate,
+        r#"
+        UPDATE note_duplicates
+        SET dismissed_at = NOW(), dismissed_by_user_id = $2
+        WHERE id = $1
+        RETURNING *
+        "#,
+        duplicate_id,
+        user_id
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(duplicate)
+}
+
+/// Merges the note `remove_note_id` into `keep_note_id` and deletes it.
+///
+/// Votes, bookmarks, collection entries and professors move to the kept note unless it already
+/// has them, comments always move, and downloads are added up.
+pub async fn merge_notes(
+    db_wrapper: &DBPoolWrapper,
+    keep_note_id: Uuid,
+    remove_note_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE votes SET note_id = $1
+        WHERE note_id = $2 AND user_id NOT IN (SELECT user_id FROM votes WHERE note_id = $1)
+        "#,
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE bookmarks SET note_id = $1
+        WHERE note_id = $2 AND user_id NOT IN (SELECT user_id FROM bookmarks WHERE note_id = $1)
+        "#,
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE collection_notes SET note_id = $1
+        WHERE note_id = $2
+          AND collection_id NOT IN (SELECT collection_id FROM collection_notes WHERE note_id = $1)
+        "#,
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "UPDATE comments SET note_id = $1 WHERE note_id = $2",
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO note_professors (note_id, professor_id)
+        SELECT $1, professor_id FROM note_professors WHERE note_id = $2
+        ON CONFLICT (note_id, professor_id) DO NOTHING
+        "#,
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE notes
+        SET downloads = downloads + (SELECT downloads FROM notes WHERE id = $2)
+        WHERE id = $1
+        "#,
+        keep_note_id,
+        remove_note_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM notes WHERE id = $1", remove_note_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use crate::db::models::{DBJob, JobKind, JobStatus};
+use crate::db::DBPoolWrapper;
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Queues a job for a note, unless the same kind of job is already queued for it.
+pub async fn enqueue_job(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    kind: JobKind,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO jobs (kind, note_id) VALUES ($1, $2)
+        ON CONFLICT (note_id, kind) WHERE status IN ('pending', 'running') DO NOTHING
+        "#,
+        kind as JobKind,
+        note_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Claims the next job that is due. Jobs left running for longer than `lock_timeout_seconds`,
+/// e.g. by a worker that crashed, are claimed again if they have attempts left, and marked as
+/// failed otherwise, so that a job that keeps killing its worker isn't retried forever.
+pub async fn claim_job(
+    db_wrapper: &DBPoolWrapper,
+    lock_timeout_seconds: f64,
+) -> Result<Option<DBJob>, sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = 'failed',
+            locked_at = NULL,
+            last_error = 'The worker stopped responding on the last attempt',
+            updated_at = NOW()
+        WHERE status = 'running'
+          AND locked_at < NOW() - make_interval(secs => $1)
+          AND attempts >= max_attempts
+        "#,
+        lock_timeout_seconds
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    let job = sqlx::query_as!(
+        DBJob,
+        r#"
+        UPDATE jobs
+        SET status = 'running', attempts = attempts + 1, locked_at = NOW(), updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = 'pending' AND run_at <= NOW())
+                OR (status = 'running' AND locked_at < NOW() - make_interval(secs => $1) AND attempts < max_attempts)
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, kind as "kind: JobKind", note_id, status as "status: JobStatus", attempts, max_attempts,
+            run_at, locked_at, last_error, created_at, updated_at, completed_at
+        "#,
+        lock_timeout_seconds
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(job)
+}
+
+pub async fn complete_job(db_wrapper: &DBPoolWrapper, job_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = 'completed', locked_at = NULL, last_error = NULL, completed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+        job_id
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    Ok(())
+}
+
+/// Records a failed attempt. The job is retried at `retry_at`, or marked as failed if not given.
+pub async fn fail_job(
+    db_wrapper: &DBPoolWrapper,
+    job_id: Uuid,
+    error: &str,
+    retry_at: Option<DateTime<Utc>>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN 'failed'::job_status ELSE 'pending'::job_status END,
+            run_at = COALESCE($3, run_at),
+            locked_at = NULL,
+            last_error = $2,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        job_id,
+        error,
+        retry_at
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    Ok(())
+}
@@ -1,3 +1,15 @@
+pub mod audit;
+pub mod bookmarks;
+pub mod collections;
+pub mod comments;
+pub mod courses;
+pub mod duplicates;
+pub mod jobs;
 pub mod notes;
+pub mod professors;
+pub mod quarantine;
+pub mod suggestions;
+pub mod tags;
+pub mod uploads;
 pub mod users;
 pub mod votes;
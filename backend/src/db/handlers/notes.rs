@@ -1,28 +1,42 @@
 use crate::db::db::DBPoolWrapper;
-use crate::db::models::{Note, NoteWithUser};
-use sqlx::Postgres;
+use crate::db::models::{ContentType, Note, NotePreviewStatus, NoteWithUser, RelatedNoteIds, Semester};
 use uuid::Uuid;
 
-use crate::api::models::CreateNote;
-
-/// Inserts a new note record into the database.
+use crate::api::models::{CreateNote, NoteFilter};
 
+/// Sets whether a note has a preview image, and how many of its pages have previews.
 pub async fn update_note_preview_status(
-    tx: &mut sqlx::Transaction<'_, Postgres>,
+    db_wrapper: &DBPoolWrapper,
     note_id: Uuid,
     status: bool,
+    preview_pages: i32,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        "UPDATE notes SET has_preview_image = $1 WHERE id = $2",
+        "UPDATE notes SET has_preview_image = $1, preview_pages = $2 WHERE id = $3",
         status,
+        preview_pages,
         note_id
     )
-        .execute(&mut **tx)
+        .execute(db_wrapper.pool())
         .await?;
 
     Ok(())
 }
 
+pub async fn get_note_preview_statuses(
+    db_wrapper: &DBPoolWrapper,
+) -> Result<Vec<NotePreviewStatus>, sqlx::Error> {
+    let statuses = sqlx::query_as!(
+        NotePreviewStatus,
+        "SELECT id, has_preview_image, preview_pages FROM notes ORDER BY created_at"
+    )
+        .fetch_all(db_wrapper.pool())
+        .await?;
+
+    Ok(statuses)
+}
+
+/// Inserts a new note record into the database.
 pub async fn create_note(
     db_wrapper: &DBPoolWrapper,
     new_note: CreateNote,
@@ -33,9 +47,10 @@ pub async fn create_note(
     let note = sqlx::query_as!(
         Note,
         r#"
-        INSERT INTO notes (course_name, course_code, description, professor_names, tags, has_preview_image, uploader_user_id)
-        VALUES ($1, $2, $3, $4, $5, $6, $7)
-        RETURNING id, course_name, course_code, description, professor_names, tags, is_public, has_preview_image, uploader_user_id, created_at, downloads
+        INSERT INTO notes (course_name, course_code, description, professor_names, tags, has_preview_image, uploader_user_id, course_id, academic_year, semester, content_type, file_sha256, page_count, pdf_metadata)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        RETURNING id, course_name, course_code, description, professor_names, tags, is_public, has_preview_image, uploader_user_id, created_at, downloads, course_id,
+            academic_year, semester as "semester: Semester", content_type as "content_type: ContentType", file_sha256, page_count, pdf_metadata
         "#,
         new_note.course_name,
         new_note.course_code,
@@ -43,7 +58,14 @@ pub async fn create_note(
         new_note.professor_names.as_deref(),
         &new_note.tags,
         new_note.has_preview_image,
-        new_note.uploader_user_id
+        new_note.uploader_user_id,
+        new_note.course_id,
+        new_note.academic_year,
+        new_note.semester as Option<Semester>,
+        new_note.content_type as Option<ContentType>,
+        new_note.file_sha256,
+        new_note.page_count,
+        new_note.pdf_metadata
     )
         .fetch_one(&mut *tx)  // Execute on the transaction instead of the pool
         .await?;
@@ -54,6 +76,7 @@ pub async fn create_note(
 pub async fn get_notes(
     db_wrapper: &DBPoolWrapper,
     num_notes: usize,
+    filter: &NoteFilter,
     current_user_id: Option<Uuid>,
 ) -> Result<Vec<NoteWithUser>, sqlx::Error> {
     let notes = sqlx::query_as!(
@@ -71,9 +94,16 @@ pub async fn get_notes(
             n.uploader_user_id as "note_uploader_user_id!",
             n.created_at as "note_created_at!",
             n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
             COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
             COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
             user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
             u.id as "user_id!",
             u.google_id as "user_google_id!",
             u.email as "user_email!",
@@ -94,14 +124,27 @@ pub async fn get_notes(
              FROM votes
              WHERE is_upvote = false
              GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
         LEFT JOIN
             votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE ($3::int IS NULL OR n.academic_year = $3)
+          AND ($4::semester IS NULL OR n.semester = $4)
+          AND ($5::note_content_type IS NULL OR n.content_type = $5)
         ORDER BY
             n.created_at DESC
         LIMIT $1
         "#,
         num_notes as i64,
-        current_user_id.as_ref()
+        current_user_id.as_ref(),
+        filter.academic_year,
+        filter.semester as Option<Semester>,
+        filter.content_type as Option<ContentType>
     )
         .fetch_all(db_wrapper.pool())
         .await?;
@@ -111,6 +154,7 @@ pub async fn get_notes(
 pub async fn search_notes_by_query(
     db_wrapper: &DBPoolWrapper,
     query: &str,
+    filter: &NoteFilter,
     current_user_id: Option<Uuid>,
 ) -> Result<Vec<NoteWithUser>, sqlx::Error> {
     let search_term = format!("%{}%", query); // Wrap query for partial matching
@@ -129,9 +173,16 @@ pub async fn search_notes_by_query(
             n.uploader_user_id as "note_uploader_user_id!",
             n.created_at as "note_created_at!",
             n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
             COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
             COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
             user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
             u.id as "user_id!",
             u.google_id as "user_google_id!",
             u.email as "user_email!",
@@ -152,12 +203,124 @@ pub async fn search_notes_by_query(
              FROM votes
              WHERE is_upvote = false
              GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
         LEFT JOIN
             votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
-        WHERE course_name ILIKE $1 OR course_code ILIKE $1
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE (n.course_name ILIKE $1 OR n.course_code ILIKE $1)
+          AND ($3::int IS NULL OR n.academic_year = $3)
+          AND ($4::semester IS NULL OR n.semester = $4)
+          AND ($5::note_content_type IS NULL OR n.content_type = $5)
         "#,
         search_term,
-        current_user_id.as_ref()
+        current_user_id.as_ref(),
+        filter.academic_year,
+        filter.semester as Option<Semester>,
+        filter.content_type as Option<ContentType>
+    )
+        .fetch_all(db_wrapper.pool())
+        .await?;
+    Ok(notes)
+}
+
+/// Typo-tolerant search on course names, course codes and professor names using trigram similarity.
+///
+/// Results are ordered by how closely they match the query.
+pub async fn fuzzy_search_notes(
+    db_wrapper: &DBPoolWrapper,
+    query: &str,
+    filter: &NoteFilter,
+    num_notes: usize,
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            notes n
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT np.note_id, MAX(GREATEST(similarity(a.alias, $1), word_similarity($1, a.alias))) as score
+             FROM note_professors np
+             JOIN professor_aliases a ON np.professor_id = a.professor_id
+             WHERE a.alias % $1 OR $1 <% a.alias
+             GROUP BY np.note_id) professor_matches ON n.id = professor_matches.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE (n.course_name % $1
+               OR $1 <% n.course_name
+               OR n.course_code % $1
+               OR professor_matches.score IS NOT NULL)
+          AND ($3::int IS NULL OR n.academic_year = $3)
+          AND ($4::semester IS NULL OR n.semester = $4)
+          AND ($5::note_content_type IS NULL OR n.content_type = $5)
+        ORDER BY
+            GREATEST(
+                similarity(n.course_name, $1),
+                word_similarity($1, n.course_name),
+                similarity(n.course_code, $1),
+                COALESCE(professor_matches.score, 0)
+            ) DESC,
+            n.created_at DESC
+        LIMIT $6
+        "#,
+        query,
+        current_user_id.as_ref(),
+        filter.academic_year,
+        filter.semester as Option<Semester>,
+        filter.content_type as Option<ContentType>,
+        num_notes as i64
     )
         .fetch_all(db_wrapper.pool())
         .await?;
@@ -184,9 +347,16 @@ pub async fn get_note_by_id(
         n.uploader_user_id as "note_uploader_user_id!",
         n.created_at as "note_created_at!",
         n.downloads as "note_downloads!",
+        n.academic_year as "note_academic_year",
+        n.semester as "note_semester: Semester",
+        n.content_type as "note_content_type: ContentType",
+        n.page_count as "note_page_count",
+        n.preview_pages as "note_preview_pages",
         COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
         COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+        COALESCE(comment_counts.count, 0) as "note_comment_count!",
         user_vote.is_upvote as "note_user_upvote?",
+        user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
         u.id as "user_id!",
         u.google_id as "user_google_id!",
         u.email as "user_email!",
@@ -207,8 +377,15 @@ pub async fn get_note_by_id(
          FROM votes
          WHERE is_upvote = false
          GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+    LEFT JOIN
+        (SELECT note_id, COUNT(*) as count
+         FROM comments
+         WHERE deleted_at IS NULL
+         GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
     LEFT JOIN
         votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+    LEFT JOIN
+        bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
     WHERE n.id = $1
     "#,
         note_id,
@@ -219,6 +396,164 @@ pub async fn get_note_by_id(
     Ok(note_with_user)
 }
 
+/// Fetches the given notes. Missing notes are skipped and the order is unspecified.
+pub async fn get_notes_by_ids(
+    db_wrapper: &DBPoolWrapper,
+    note_ids: &[Uuid],
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            notes n
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE n.id = ANY($1)
+        "#,
+        note_ids,
+        current_user_id.as_ref()
+    )
+        .fetch_all(db_wrapper.pool())
+        .await?;
+    Ok(notes)
+}
+
+/// Finds notes related to a note: other notes of the same course, notes by the same professors
+/// from other courses, and notes that users who upvoted or bookmarked this note also upvoted or bookmarked.
+///
+/// Returns `None` if the note doesn't exist.
+pub async fn get_related_note_ids(
+    db_wrapper: &DBPoolWrapper,
+    note_id: Uuid,
+    num_per_kind: usize,
+) -> Result<Option<RelatedNoteIds>, sqlx::Error> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM notes WHERE id = $1) as "exists!""#,
+        note_id
+    )
+        .fetch_one(db_wrapper.pool())
+        .await?;
+    if !exists {
+        return Ok(None);
+    }
+
+    let rows = sqlx::query!(
+        r#"
+        WITH target AS (
+            SELECT id, course_code FROM notes WHERE id = $1
+        ),
+        upvote_counts AS (
+            SELECT note_id, COUNT(*) as count
+            FROM votes
+            WHERE is_upvote = true
+            GROUP BY note_id
+        ),
+        interactions AS (
+            SELECT user_id, note_id FROM votes WHERE is_upvote = true
+            UNION
+            SELECT user_id, note_id FROM bookmarks
+        )
+        SELECT
+            related.kind as "kind!",
+            related.note_id as "note_id!"
+        FROM
+            ((SELECT 'same_course' as kind, n.id as note_id,
+                     ROW_NUMBER() OVER (ORDER BY COALESCE(uc.count, 0) DESC, n.created_at DESC) as rank
+              FROM notes n
+              JOIN target t ON n.course_code = t.course_code AND n.id <> t.id
+              LEFT JOIN upvote_counts uc ON n.id = uc.note_id
+              ORDER BY rank
+              LIMIT $2)
+             UNION ALL
+             (SELECT 'same_professor', n.id,
+                     ROW_NUMBER() OVER (ORDER BY COUNT(*) DESC, COALESCE(uc.count, 0) DESC, n.created_at DESC)
+              FROM note_professors target_np
+              JOIN note_professors np ON np.professor_id = target_np.professor_id AND np.note_id <> target_np.note_id
+              JOIN notes n ON np.note_id = n.id
+              JOIN target t ON n.course_code <> t.course_code
+              LEFT JOIN upvote_counts uc ON n.id = uc.note_id
+              WHERE target_np.note_id = $1
+              GROUP BY n.id, uc.count
+              ORDER BY 3
+              LIMIT $2)
+             UNION ALL
+             (SELECT 'also_liked', other.note_id,
+                     ROW_NUMBER() OVER (ORDER BY COUNT(*) DESC, other.note_id)
+              FROM interactions mine
+              JOIN interactions other ON other.user_id = mine.user_id AND other.note_id <> mine.note_id
+              WHERE mine.note_id = $1
+              GROUP BY other.note_id
+              ORDER BY 3
+              LIMIT $2)) related
+        ORDER BY
+            related.kind, related.rank
+        "#,
+        note_id,
+        num_per_kind as i64
+    )
+        .fetch_all(db_wrapper.pool())
+        .await?;
+
+    let mut related = RelatedNoteIds::default();
+    for row in rows {
+        match row.kind.as_str() {
+            "same_course" => related.same_course.push(row.note_id),
+            "same_professor" => related.same_professor.push(row.note_id),
+            _ => related.also_liked.push(row.note_id),
+        }
+    }
+
+    Ok(Some(related))
+}
+
 pub async fn increment_note_downloads(
     db_wrapper: &DBPoolWrapper,
     note_id: Uuid,
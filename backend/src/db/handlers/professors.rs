@@ -0,0 +1,382 @@
+use crate::db::models::{
+    ContentType, DBProfessor, NoteWithUser, ProfessorAliasChange, ProfessorCandidate,
+    ProfessorWithNoteCount, Semester,
+};
+use crate::db::DBPoolWrapper;
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Minimum trigram similarity for an existing professor to be suggested for an uploaded name.
+const PROFESSOR_SUGGESTION_THRESHOLD: f32 = 0.6;
+
+/// Finds the professor with `name` as their name or one of their aliases, ignoring case.
+pub async fn find_professor_by_alias(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    name: &str,
+) -> Result<Option<DBProfessor>, sqlx::Error> {
+    let professor = sqlx::query_as!(
+        DBProfessor,
+        r#"
+        SELECT p.*
+        FROM professor_aliases a
+        JOIN professors p ON a.professor_id = p.id
+        WHERE a.alias = $1
+        "#,
+        name.trim().to_lowercase()
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(professor)
+}
+
+/// Finds the professors whose name or aliases are similar to `name`, most similar first, so the
+/// upload form can suggest them for a possibly misspelled name.
+pub async fn find_similar_professors(
+    db_wrapper: &DBPoolWrapper,
+    name: &str,
+    limit: usize,
+) -> Result<Vec<ProfessorCandidate>, sqlx::Error> {
+    let candidates = sqlx::query_as!(
+        ProfessorCandidate,
+        r#"
+        SELECT
+            p.id,
+            p.name,
+            MAX(similarity(a.alias, $1)) as "score!",
+            BOOL_OR(a.alias = $1) as "exact!"
+        FROM
+            professor_aliases a
+        JOIN
+            professors p ON a.professor_id = p.id
+        WHERE a.alias % $1 OR a.alias = $1
+        GROUP BY
+            p.id
+        HAVING MAX(similarity(a.alias, $1)) >= $2 OR BOOL_OR(a.alias = $1)
+        ORDER BY
+            3 DESC, p.name ASC
+        LIMIT $3
+        "#,
+        name.trim().to_lowercase(),
+        PROFESSOR_SUGGESTION_THRESHOLD,
+        limit as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(candidates)
+}
+
+/// Creates a professor with `name` as their alias. If a concurrent upload created a professor with
+/// the same alias first, that professor is returned instead.
+async fn create_professor(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    name: &str,
+) -> Result<DBProfessor, sqlx::Error> {
+    let professor = sqlx::query_as!(
+        DBProfessor,
+        "INSERT INTO professors (name) VALUES ($1) RETURNING *",
+        name
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    // The insert waits for a concurrent one of the same alias to commit, then returns its professor
+    let alias_professor_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO professor_aliases (alias, professor_id) VALUES ($1, $2)
+        ON CONFLICT (alias) DO UPDATE SET alias = EXCLUDED.alias
+        RETURNING professor_id
+        "#,
+        name.trim().to_lowercase(),
+        professor.id
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    if alias_professor_id == professor.id {
+        return Ok(professor);
+    }
+
+    sqlx::query!("DELETE FROM professors WHERE id = $1", professor.id)
+        .execute(&mut **tx)
+        .await?;
+    sqlx::query_as!(
+        DBProfessor,
+        "SELECT * FROM professors WHERE id = $1",
+        alias_professor_id
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Links a note to its professors. Names are only matched exactly against existing professors'
+/// names and aliases, anything else creates a new professor; misspellings are caught by the
+/// upload form suggesting [`find_similar_professors`] instead.
+///
+/// Returns the canonical names, which are also stored on the note.
+pub async fn link_note_professors(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    names: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let mut canonical_names: Vec<String> = Vec::new();
+
+    for name in names {
+        let professor = match find_professor_by_alias(tx, name).await? {
+            Some(professor) => professor,
+            None => create_professor(tx, name).await?,
+        };
+
+        sqlx::query!(
+            r#"
+            INSERT INTO note_professors (note_id, professor_id) VALUES ($1, $2)
+            ON CONFLICT (note_id, professor_id) DO NOTHING
+            "#,
+            note_id,
+            professor.id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        if !canonical_names.contains(&professor.name) {
+            canonical_names.push(professor.name);
+        }
+    }
+
+    sqlx::query!(
+        "UPDATE notes SET professor_names = $1 WHERE id = $2",
+        &canonical_names,
+        note_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(canonical_names)
+}
+
+/// Adds `alias` to a professor. If the alias already belongs to another professor, that professor
+/// is merged into this one: their aliases and notes move over, and notes are renamed to this
+/// professor's name. Returns `None` if the professor doesn't exist.
+pub async fn add_professor_alias(
+    db_wrapper: &DBPoolWrapper,
+    professor_id: Uuid,
+    alias: &str,
+) -> Result<Option<ProfessorAliasChange>, sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    let Some(professor) = sqlx::query_as!(
+        DBProfessor,
+        "SELECT * FROM professors WHERE id = $1 FOR UPDATE",
+        professor_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let alias_professor_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO professor_aliases (alias, professor_id) VALUES ($1, $2)
+        ON CONFLICT (alias) DO UPDATE SET alias = EXCLUDED.alias
+        RETURNING professor_id
+        "#,
+        alias,
+        professor.id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut merged_professor = None;
+    if alias_professor_id != professor.id {
+        let merged = sqlx::query_as!(
+            DBProfessor,
+            "SELECT * FROM professors WHERE id = $1 FOR UPDATE",
+            alias_professor_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE professor_aliases SET professor_id = $1 WHERE professor_id = $2",
+            professor.id,
+            merged.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        // Rename the merged professor on their notes, keeping the order of the names
+        sqlx::query!(
+            r#"
+            UPDATE notes n
+            SET professor_names = ARRAY(
+                SELECT names.name
+                FROM UNNEST(ARRAY_REPLACE(n.professor_names, $2, $3)) WITH ORDINALITY AS names(name, position)
+                GROUP BY names.name
+                ORDER BY MIN(names.position)
+            )
+            FROM note_professors np
+            WHERE np.note_id = n.id AND np.professor_id = $1
+            "#,
+            merged.id,
+            merged.name,
+            professor.name
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO note_professors (note_id, professor_id)
+            SELECT note_id, $1 FROM note_professors WHERE professor_id = $2
+            ON CONFLICT (note_id, professor_id) DO NOTHING
+            "#,
+            professor.id,
+            merged.id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM professors WHERE id = $1", merged.id)
+            .execute(&mut *tx)
+            .await?;
+
+        merged_professor = Some(merged);
+    }
+
+    tx.commit().await?;
+
+    Ok(Some(ProfessorAliasChange {
+        professor,
+        alias: alias.to_string(),
+        merged_professor,
+    }))
+}
+
+pub async fn get_professor(
+    db_wrapper: &DBPoolWrapper,
+    professor_id: Uuid,
+) -> Result<Option<DBProfessor>, sqlx::Error> {
+    let professor = sqlx::query_as!(
+        DBProfessor,
+        "SELECT * FROM professors WHERE id = $1",
+        professor_id
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(professor)
+}
+
+/// Lists professors with their note counts. With a query, professors are ranked by how closely
+/// their name or aliases match it, so misspelled names still find the right professor.
+pub async fn get_professors(
+    db_wrapper: &DBPoolWrapper,
+    query: Option<&str>,
+    num_professors: usize,
+) -> Result<Vec<ProfessorWithNoteCount>, sqlx::Error> {
+    let query = query.map(str::to_lowercase);
+    let prefix = query.as_ref().map(|q| format!("{}%", q));
+    let professors = sqlx::query_as!(
+        ProfessorWithNoteCount,
+        r#"
+        SELECT
+            p.id,
+            p.name,
+            (SELECT COUNT(*) FROM note_professors np WHERE np.professor_id = p.id) as "note_count!"
+        FROM
+            professors p
+        LEFT JOIN LATERAL
+            (SELECT MAX(similarity(a.alias, $1)) as score
+             FROM professor_aliases a
+             WHERE a.professor_id = p.id
+               AND (a.alias % $1 OR a.alias LIKE $2)) matches ON TRUE
+        WHERE $1::text IS NULL OR matches.score IS NOT NULL
+        ORDER BY
+            matches.score DESC NULLS LAST, 3 DESC, p.name ASC
+        LIMIT $3
+        "#,
+        query,
+        prefix,
+        num_professors as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(professors)
+}
+
+/// Lists the notes linked to a professor, newest first.
+pub async fn get_professor_notes(
+    db_wrapper: &DBPoolWrapper,
+    professor_id: Uuid,
+    current_user_id: Option<Uuid>,
+) -> Result<Vec<NoteWithUser>, sqlx::Error> {
+    let notes = sqlx::query_as!(
+        NoteWithUser,
+        r#"
+        SELECT
+            n.id as "note_id!",
+            n.course_name as "note_course_name!",
+            n.course_code as "note_course_code!",
+            n.description as "note_description",
+            n.professor_names as "note_professor_names",
+            n.tags as "note_tags!",
+            n.is_public as "note_is_public!",
+            n.has_preview_image as "note_has_preview_image!",
+            n.uploader_user_id as "note_uploader_user_id!",
+            n.created_at as "note_created_at!",
+            n.downloads as "note_downloads!",
+            n.academic_year as "note_academic_year",
+            n.semester as "note_semester: Semester",
+            n.content_type as "note_content_type: ContentType",
+            n.page_count as "note_page_count",
+            n.preview_pages as "note_preview_pages",
+            COALESCE(upvote_counts.count, 0) as "note_upvote_count!",
+            COALESCE(downvote_counts.count, 0) as "note_downvote_count!",
+            COALESCE(comment_counts.count, 0) as "note_comment_count!",
+            user_vote.is_upvote as "note_user_upvote?",
+            user_bookmark.id IS NOT NULL as "note_user_bookmarked!",
+            u.id as "user_id!",
+            u.google_id as "user_google_id!",
+            u.email as "user_email!",
+            u.full_name as "user_full_name!",
+            u.reputation as "user_reputation!",
+            u.created_at as "user_created_at!"
+        FROM
+            note_professors np
+        JOIN
+            notes n ON np.note_id = n.id
+        JOIN
+            users u ON n.uploader_user_id = u.id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = true
+             GROUP BY note_id) upvote_counts ON n.id = upvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM votes
+             WHERE is_upvote = false
+             GROUP BY note_id) downvote_counts ON n.id = downvote_counts.note_id
+        LEFT JOIN
+            (SELECT note_id, COUNT(*) as count
+             FROM comments
+             WHERE deleted_at IS NULL
+             GROUP BY note_id) comment_counts ON n.id = comment_counts.note_id
+        LEFT JOIN
+            votes user_vote ON n.id = user_vote.note_id AND user_vote.user_id = $2
+        LEFT JOIN
+            bookmarks user_bookmark ON n.id = user_bookmark.note_id AND user_bookmark.user_id = $2
+        WHERE np.professor_id = $1
+        ORDER BY
+            n.created_at DESC
+        "#,
+        professor_id,
+        current_user_id.as_ref()
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(notes)
+}
@@ -0,0 +1,49 @@
+use crate::db::models::DBQuarantinedFile;
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+/// Records an infected upload whose file was moved to `file_key` in the quarantine directory.
+pub async fn insert_quarantined_file(
+    db_wrapper: &DBPoolWrapper,
+    id: Uuid,
+    file_key: &str,
+    file_sha256: &str,
+    signature: &str,
+    uploader_user_id: Uuid,
+    course_code: &str,
+) -> Result<DBQuarantinedFile, sqlx::Error> {
+    let quarantined_file = sqlx::query_as!(
+        DBQuarantinedFile,
+        r#"
+        INSERT INTO quarantined_files (id, file_key, file_sha256, signature, uploader_user_id, course_code)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+        id,
+        file_key,
+        file_sha256,
+        signature,
+        uploader_user_id,
+        course_code
+    )
+    .fetch_one(db_wrapper.pool())
+    .await?;
+
+    Ok(quarantined_file)
+}
+
+/// Lists quarantined uploads, newest first.
+pub async fn get_quarantined_files(
+    db_wrapper: &DBPoolWrapper,
+    num_files: usize,
+) -> Result<Vec<DBQuarantinedFile>, sqlx::Error> {
+    let quarantined_files = sqlx::query_as!(
+        DBQuarantinedFile,
+        "SELECT * FROM quarantined_files ORDER BY created_at DESC LIMIT $1",
+        num_files as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(quarantined_files)
+}
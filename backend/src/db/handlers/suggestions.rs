@@ -0,0 +1,144 @@
+use crate::db::models::Suggestion;
+use crate::db::DBPoolWrapper;
+
+/// Finds course codes, course names, professors and tags matching a partial search query.
+///
+/// Course codes and tags are matched by prefix. Course names and professors are also matched by
+/// word prefix and trigram similarity, so typos still find them. Prefix matches come first,
+/// then the most popular results by number of notes.
+pub async fn get_suggestions(
+    db_wrapper: &DBPoolWrapper,
+    query: &str,
+    course_code_prefix: Option<&str>,
+    num_suggestions: usize,
+) -> Result<Vec<Suggestion>, sqlx::Error> {
+    let query = query.to_lowercase();
+    let code_pattern = course_code_prefix.map(|prefix| format!("{}%", prefix));
+    let prefix_pattern = format!("{}%", query);
+    let word_prefix_pattern = format!("% {}%", query);
+    let suggestions = sqlx::query_as!(
+        Suggestion,
+        r#"
+        WITH matches AS (
+            SELECT
+                'course_code' as kind,
+                codes.code::text as value,
+                MAX(codes.name)::text as label,
+                SUM(codes.note_count)::bigint as popularity,
+                TRUE as prefix_match,
+                1.0::real as score
+            FROM
+                (SELECT course_code as code, course_name as name, 1 as note_count
+                 FROM notes
+                 WHERE course_code LIKE $2
+                 UNION ALL
+                 SELECT code, name, 0
+                 FROM courses
+                 WHERE code LIKE $2) codes
+            GROUP BY
+                codes.code
+            UNION ALL
+            SELECT
+                'course_name',
+                names.name::text,
+                MAX(names.code)::text,
+                SUM(names.note_count)::bigint,
+                BOOL_OR(LOWER(names.name) LIKE $3 OR LOWER(names.name) LIKE $4),
+                MAX(similarity(names.name, $1))
+            FROM
+                (SELECT course_name as name, course_code as code, 1 as note_count
+                 FROM notes
+                 WHERE course_name % $1 OR course_name ILIKE $3 OR course_name ILIKE $4
+                 UNION ALL
+                 SELECT name, code, 0
+                 FROM courses
+                 WHERE name % $1 OR name ILIKE $3 OR name ILIKE $4) names
+            GROUP BY
+                names.name
+            UNION ALL
+            SELECT
+                'professor',
+                p.name::text,
+                NULL::text,
+                (SELECT COUNT(*) FROM note_professors np WHERE np.professor_id = p.id),
+                BOOL_OR(a.alias LIKE $3 OR a.alias LIKE $4),
+                MAX(similarity(a.alias, $1))
+            FROM
+                professors p
+            JOIN
+                professor_aliases a ON a.professor_id = p.id
+            WHERE a.alias % $1 OR a.alias LIKE $3 OR a.alias LIKE $4
+            GROUP BY
+                p.id
+            UNION ALL
+            SELECT
+                'tag',
+                t.tag,
+                NULL::text,
+                COUNT(*),
+                TRUE,
+                1.0::real
+            FROM
+                notes n
+            CROSS JOIN LATERAL
+                UNNEST(n.tags) AS t(tag)
+            WHERE t.tag LIKE $3
+               OR t.tag IN (SELECT tag FROM tag_synonyms WHERE synonym LIKE $3)
+            GROUP BY
+                t.tag
+        )
+        SELECT
+            kind as "kind!",
+            value as "value!",
+            label,
+            popularity as "popularity!"
+        FROM
+            matches
+        ORDER BY
+            prefix_match DESC, popularity DESC, score DESC, value ASC
+        LIMIT $5
+        "#,
+        query,
+        code_pattern,
+        prefix_pattern,
+        word_prefix_pattern,
+        num_suggestions as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(suggestions)
+}
+
+/// Finds the course name, course code or professor name closest to a query that found few notes,
+/// for a "did you mean" hint. Returns `None` if nothing is similar enough or the query is already exact.
+pub async fn get_search_correction(
+    db_wrapper: &DBPoolWrapper,
+    query: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let correction = sqlx::query_scalar!(
+        r#"
+        SELECT
+            candidates.term as "term!"
+        FROM
+            (SELECT course_name::text as term FROM notes WHERE course_name % $1 OR $1 <% course_name
+             UNION
+             SELECT course_code::text FROM notes WHERE course_code % $1
+             UNION
+             SELECT p.name::text
+             FROM professors p
+             JOIN professor_aliases a ON a.professor_id = p.id
+             WHERE a.alias % $1 OR $1 <% a.alias) candidates
+        WHERE LOWER(candidates.term) <> LOWER($1)
+        ORDER BY
+            GREATEST(similarity(candidates.term, $1), word_similarity($1, candidates.term)) DESC,
+            candidates.term ASC
+        LIMIT 1
+        "#,
+        query
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(correction)
+}
@@ -0,0 +1,156 @@
+use crate::db::models::{DBTagSynonym, TagWithNoteCount};
+use crate::db::DBPoolWrapper;
+use uuid::Uuid;
+
+/// Maps normalized tags through the synonym table, dropping duplicates while keeping their order.
+pub async fn resolve_tags(
+    db_wrapper: &DBPoolWrapper,
+    tags: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let synonyms = sqlx::query_as!(
+        DBTagSynonym,
+        "SELECT * FROM tag_synonyms WHERE synonym = ANY($1)",
+        tags
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    let mut resolved: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = synonyms
+            .iter()
+            .find(|s| &s.synonym == tag)
+            .map_or(tag, |s| &s.tag);
+        if !resolved.contains(tag) {
+            resolved.push(tag.clone());
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Lists tags with the number of notes using them, most used first.
+///
+/// With a prefix, tags starting with it are returned, along with tags that have a synonym starting with it.
+pub async fn get_tags(
+    db_wrapper: &DBPoolWrapper,
+    prefix: Option<&str>,
+    num_tags: usize,
+) -> Result<Vec<TagWithNoteCount>, sqlx::Error> {
+    let pattern = prefix.map(|prefix| format!("{}%", prefix));
+    let tags = sqlx::query_as!(
+        TagWithNoteCount,
+        r#"
+        SELECT
+            t.tag as "tag!",
+            COUNT(*) as "note_count!"
+        FROM
+            notes n
+        CROSS JOIN LATERAL
+            UNNEST(n.tags) AS t(tag)
+        WHERE $1::text IS NULL
+           OR t.tag LIKE $1
+           OR t.tag IN (SELECT tag FROM tag_synonyms WHERE synonym LIKE $1)
+        GROUP BY
+            t.tag
+        ORDER BY
+            2 DESC, 1 ASC
+        LIMIT $2
+        "#,
+        pattern,
+        num_tags as i64
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(tags)
+}
+
+pub async fn get_tag_synonyms(db_wrapper: &DBPoolWrapper) -> Result<Vec<DBTagSynonym>, sqlx::Error> {
+    let synonyms = sqlx::query_as!(
+        DBTagSynonym,
+        "SELECT * FROM tag_synonyms ORDER BY tag, synonym"
+    )
+    .fetch_all(db_wrapper.pool())
+    .await?;
+
+    Ok(synonyms)
+}
+
+/// Merges `synonym` into `tag`, rewriting existing notes and synonyms that pointed to `synonym`.
+///
+/// If `tag` is itself a synonym, the merge follows it to its canonical tag. Returns `None`
+/// without changing anything if that canonical tag is `synonym`, i.e. `tag` is already merged
+/// into `synonym`.
+pub async fn add_tag_synonym(
+    db_wrapper: &DBPoolWrapper,
+    synonym: &str,
+    tag: &str,
+    created_by_user_id: Uuid,
+) -> Result<Option<DBTagSynonym>, sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    let tag = sqlx::query_scalar!("SELECT tag FROM tag_synonyms WHERE synonym = $1", tag)
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or_else(|| tag.to_string());
+    if tag == synonym {
+        return Ok(None);
+    }
+
+    sqlx::query!(
+        "UPDATE tag_synonyms SET tag = $2 WHERE tag = $1",
+        synonym,
+        tag
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let tag_synonym = sqlx::query_as!(
+        DBTagSynonym,
+        r#"
+        INSERT INTO tag_synonyms (synonym, tag, created_by_user_id) VALUES ($1, $2, $3)
+        ON CONFLICT (synonym) DO UPDATE SET tag = EXCLUDED.tag, created_by_user_id = EXCLUDED.created_by_user_id
+        RETURNING *
+        "#,
+        synonym,
+        tag,
+        created_by_user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE notes n
+        SET tags = ARRAY(
+            SELECT merged.tag
+            FROM (SELECT CASE WHEN t.tag = $1 THEN $2 ELSE t.tag END AS tag, t.position
+                  FROM UNNEST(n.tags) WITH ORDINALITY AS t(tag, position)) merged
+            GROUP BY merged.tag
+            ORDER BY MIN(merged.position)
+        )
+        WHERE $1 = ANY(n.tags)
+        "#,
+        synonym,
+        tag
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(tag_synonym))
+}
+
+/// Removes a synonym. Notes that were already merged keep the canonical tag.
+pub async fn remove_tag_synonym(
+    db_wrapper: &DBPoolWrapper,
+    synonym: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM tag_synonyms WHERE synonym = $1", synonym)
+        .execute(db_wrapper.pool())
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
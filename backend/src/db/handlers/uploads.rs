@@ -0,0 +1,163 @@
+use crate::db::models::DBUploadSession;
+use crate::db::DBPoolWrapper;
+use chrono::{DateTime, Utc};
+use sqlx::Postgres;
+use uuid::Uuid;
+
+/// Starts an upload session, unless the user already has `max_open_sessions` sessions that
+/// haven't expired, in which case `None` is returned.
+pub async fn create_upload_session(
+    db_wrapper: &DBPoolWrapper,
+    user_id: Uuid,
+    total_size: i64,
+    metadata: serde_json::Value,
+    expires_at: DateTime<Utc>,
+    max_open_sessions: i64,
+) -> Result<Option<DBUploadSession>, sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+
+    // Locking the user makes concurrent requests count the sessions one at a time
+    sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    let open_sessions = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) as "count!" FROM upload_sessions WHERE user_id = $1 AND expires_at > NOW()"#,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if open_sessions >= max_open_sessions {
+        return Ok(None);
+    }
+
+    let session = sqlx::query_as!(
+        DBUploadSession,
+        r#"
+        INSERT INTO upload_sessions (user_id, total_size, metadata, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+        "#,
+        user_id,
+        total_size,
+        metadata,
+        expires_at
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    Ok(Some(session))
+}
+
+/// Fetches one of the user's upload sessions, including expired ones that haven't been cleaned up yet.
+pub async fn get_upload_session(
+    db_wrapper: &DBPoolWrapper,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<DBUploadSession>, sqlx::Error> {
+    let session = sqlx::query_as!(
+        DBUploadSession,
+        "SELECT * FROM upload_sessions WHERE id = $1 AND user_id = $2",
+        session_id,
+        user_id
+    )
+    .fetch_optional(db_wrapper.pool())
+    .await?;
+
+    Ok(session)
+}
+
+/// Fetches and locks an upload session until the returned transaction ends, so that chunks for
+/// the same session are written one at a time.
+pub async fn lock_upload_session(
+    db_wrapper: &DBPoolWrapper,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<(sqlx::Transaction<'_, Postgres>, Option<DBUploadSession>), sqlx::Error> {
+    let mut tx = db_wrapper.pool().begin().await?;
+    let session = sqlx::query_as!(
+        DBUploadSession,
+        "SELECT * FROM upload_sessions WHERE id = $1 AND user_id = $2 FOR UPDATE",
+        session_id,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    Ok((tx, session))
+}
+
+pub async fn set_upload_session_progress(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    session_id: Uuid,
+    received_size: i64,
+    expires_at: DateTime<Utc>,
+) -> Result<DBUploadSession, sqlx::Error> {
+    let session = sqlx::query_as!(
+        DBUploadSession,
+        r#"
+        UPDATE upload_sessions
+        SET received_size = $2, expires_at = $3, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        session_id,
+        received_size,
+        expires_at
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(session)
+}
+
+/// Marks an upload session as being completed, pushing back its expiry so that it isn't cleaned
+/// up while the note is created.
+pub async fn mark_upload_session_completing(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    session_id: Uuid,
+    expires_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE upload_sessions SET completing = TRUE, expires_at = $2, updated_at = NOW() WHERE id = $1",
+        session_id,
+        expires_at
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Clears the completing mark of an upload session whose note couldn't be created, so that
+/// completing it can be retried.
+pub async fn reset_upload_session_completing(db_wrapper: &DBPoolWrapper, session_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE upload_sessions SET completing = FALSE, updated_at = NOW() WHERE id = $1",
+        session_id
+    )
+    .execute(db_wrapper.pool())
+    .await?;
+
+    Ok(())
+}
+
+pub async fn delete_upload_session(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    session_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM upload_sessions WHERE id = $1", session_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// Deletes expired upload sessions, returning their ids so their partial files can be removed.
+pub async fn delete_expired_upload_sessions(db_wrapper: &DBPoolWrapper) -> Result<Vec<Uuid>, sqlx::Error> {
+    let session_ids = sqlx::query_scalar!("DELETE FROM upload_sessions WHERE expires_at < NOW() RETURNING id")
+        .fetch_all(db_wrapper.pool())
+        .await?;
+
+    Ok(session_ids)
+}
@@ -1,5 +1,4 @@
-use crate::api::handlers::votes::VoteType;
-use crate::db::models::DBVote;
+use crate::db::models::{DBVote, VoteType};
 use crate::db::DBPoolWrapper;
 use uuid::Uuid;
 
@@ -11,6 +11,25 @@ pub struct DBVote {
     pub created_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Deserialize)]
+pub enum VoteType {
+    Upvote(bool),
+    Remove
+}
+
+impl VoteType {
+    /// Parses the `vote_type` query parameter shared by all voting endpoints.
+    pub fn from_query(vote_type: &str) -> Option<Self> {
+        match vote_type {
+            "upvote" => Some(VoteType::Upvote(true)),
+            // Remove
+            // "downvote" => Some(VoteType::Upvote(false)),
+            "remove" => Some(VoteType::Remove),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
 pub struct User {
     pub id: Uuid,
@@ -20,6 +39,58 @@ pub struct User {
     pub reputation: i32,
     pub created_at: DateTime<Utc>,
     pub picture: String,
+    pub is_admin: bool,
+}
+
+/// Half of the academic year a note is from.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "semester", rename_all = "snake_case")]
+pub enum Semester {
+    Autumn,
+    Spring,
+}
+
+impl std::str::FromStr for Semester {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "autumn" => Ok(Semester::Autumn),
+            "spring" => Ok(Semester::Spring),
+            _ => Err(format!("Invalid semester: {}. Expected autumn or spring", s)),
+        }
+    }
+}
+
+/// What kind of material a note contains.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "note_content_type", rename_all = "snake_case")]
+pub enum ContentType {
+    ClassNotes,
+    TutorialSolutions,
+    Midsem,
+    Endsem,
+    Assignment,
+}
+
+impl std::str::FromStr for ContentType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "class_notes" => Ok(ContentType::ClassNotes),
+            "tutorial_solutions" => Ok(ContentType::TutorialSolutions),
+            "midsem" => Ok(ContentType::Midsem),
+            "endsem" => Ok(ContentType::Endsem),
+            "assignment" => Ok(ContentType::Assignment),
+            _ => Err(format!(
+                "Invalid content type: {}. Expected one of class_notes, tutorial_solutions, midsem, endsem, assignment",
+                s
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
@@ -35,6 +106,13 @@ pub struct Note {
     pub uploader_user_id: Uuid,
     pub created_at: DateTime<Utc>,
     pub downloads: i64,
+    pub course_id: Option<Uuid>,
+    pub academic_year: Option<i32>,
+    pub semester: Option<Semester>,
+    pub content_type: Option<ContentType>,
+    pub file_sha256: Option<String>,
+    pub page_count: Option<i32>,
+    pub pdf_metadata: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,8 +129,58 @@ pub struct NoteWithUser {
     pub note_created_at: DateTime<Utc>,
     pub note_upvote_count: i64,
     pub note_downvote_count: i64,
+    pub note_comment_count: i64,
     pub note_user_upvote: Option<bool>,
+    pub note_user_bookmarked: bool,
     pub note_downloads: i64,
+    pub note_academic_year: Option<i32>,
+    pub note_semester: Option<Semester>,
+    pub note_content_type: Option<ContentType>,
+    pub note_page_count: Option<i32>,
+    pub note_preview_pages: i32,
+    pub user_id: Uuid,
+    pub user_google_id: String,
+    pub user_email: String,
+    pub user_full_name: String,
+    pub user_reputation: i32,
+    pub user_created_at: DateTime<Utc>,
+}
+
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBBookmark {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub note_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBCollection {
+    pub id: Uuid,
+    pub owner_user_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub share_token: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionWithOwner {
+    pub collection_id: Uuid,
+    pub collection_name: String,
+    pub collection_description: Option<String>,
+    pub collection_is_public: bool,
+    pub collection_share_token: Uuid,
+    pub collection_owner_user_id: Uuid,
+    pub collection_created_at: DateTime<Utc>,
+    pub collection_updated_at: DateTime<Utc>,
+    pub collection_note_count: i64,
+    pub collection_upvote_count: i64,
+    pub collection_downvote_count: i64,
+    pub collection_user_upvote: Option<bool>,
     pub user_id: Uuid,
     pub user_google_id: String,
     pub user_email: String,
@@ -61,11 +189,251 @@ pub struct NoteWithUser {
     pub user_created_at: DateTime<Utc>,
 }
 
-#[derive(Serialize, Deserialize, Debug, sqlx::FromRow)]
-pub struct Vote {
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBCollectionVote {
     pub id: Uuid,
     pub user_id: Uuid,
+    pub collection_id: Uuid,
+    pub is_upvote: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBComment {
+    pub id: Uuid,
     pub note_id: Uuid,
-    pub vote_type: String,
+    pub author_user_id: Uuid,
+    pub parent_comment_id: Option<Uuid>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub removed_by_user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentWithAuthor {
+    pub comment_id: Uuid,
+    pub comment_note_id: Uuid,
+    pub comment_parent_comment_id: Option<Uuid>,
+    pub comment_body: String,
+    pub comment_created_at: DateTime<Utc>,
+    pub comment_edited_at: Option<DateTime<Utc>>,
+    pub comment_deleted_at: Option<DateTime<Utc>>,
+    pub comment_removed_by_user_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub user_google_id: String,
+    pub user_email: String,
+    pub user_full_name: String,
+    pub user_reputation: i32,
+    pub user_created_at: DateTime<Utc>,
+}
+
+/// Normalizes user-entered course codes, e.g. "ma 10001" and "MA-10001" both become "MA10001".
+pub fn normalize_course_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Most characters in a normalized course code
+pub const MAX_COURSE_CODE_LENGTH: usize = 20;
+
+/// Checks that a normalized course code can be used. Not every course is in the catalog, so codes
+/// aren't held to the institute format, only to being non-empty and reasonably short.
+pub fn is_valid_course_code(code: &str) -> bool {
+    !code.is_empty() && code.len() <= MAX_COURSE_CODE_LENGTH
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBCourse {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub department: Option<String>,
+    pub credits: Option<i32>,
     pub created_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+/// A course as listed in the institute's catalog, used for bulk imports.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CourseRecord {
+    pub code: String,
+    pub name: String,
+    pub department: Option<String>,
+    pub credits: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CourseWithNoteCount {
+    /// `None` for course codes that notes use but that aren't in the catalog
+    pub id: Option<Uuid>,
+    pub code: String,
+    pub name: String,
+    pub department: Option<String>,
+    pub credits: Option<i32>,
+    pub note_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBProfessor {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An alias added to a professor, along with the professor it belonged to, who was merged into them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfessorAliasChange {
+    pub professor: DBProfessor,
+    pub alias: String,
+    pub merged_professor: Option<DBProfessor>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfessorWithNoteCount {
+    pub id: Uuid,
+    pub name: String,
+    pub note_count: i64,
+}
+
+/// An existing professor suggested for a name, with the trigram similarity of the closest alias.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfessorCandidate {
+    pub id: Uuid,
+    pub name: String,
+    pub score: f32,
+    /// Whether the name is exactly one of the professor's aliases
+    #[serde(skip_serializing)]
+    pub exact: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBTagSynonym {
+    pub synonym: String,
+    pub tag: String,
+    pub created_by_user_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagWithNoteCount {
+    pub tag: String,
+    pub note_count: i64,
+}
+
+/// A search suggestion. `kind` is one of `course_code`, `course_name`, `professor` or `tag`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Suggestion {
+    pub kind: String,
+    pub value: String,
+    /// Extra context for display, e.g. the course name for a course code
+    pub label: Option<String>,
+    pub popularity: i64,
+}
+
+/// Whether a note has previews, used to find notes whose previews need to be rendered again.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct NotePreviewStatus {
+    pub id: Uuid,
+    pub has_preview_image: bool,
+    pub preview_pages: i32,
+}
+
+/// Notes related to a note, most relevant first in each list.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RelatedNoteIds {
+    pub same_course: Vec<Uuid>,
+    pub same_professor: Vec<Uuid>,
+    /// Notes upvoted or bookmarked by the same users
+    pub also_liked: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBNoteDuplicate {
+    pub id: Uuid,
+    pub note_id: Uuid,
+    pub duplicate_of_note_id: Uuid,
+    pub similarity: f32,
+    pub created_at: DateTime<Utc>,
+    pub dismissed_at: Option<DateTime<Utc>>,
+    pub dismissed_by_user_id: Option<Uuid>,
+}
+
+/// An upload that malware was found in, kept in the quarantine directory instead of becoming a note.
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBQuarantinedFile {
+    pub id: Uuid,
+    pub file_key: String,
+    pub file_sha256: String,
+    /// Name of the signature clamd matched
+    pub signature: String,
+    pub uploader_user_id: Option<Uuid>,
+    pub course_code: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub actor_user_id: Option<Uuid>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub diff: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Processing done in the background after a note is uploaded.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "job_kind", rename_all = "snake_case")]
+pub enum JobKind {
+    RenderPreview,
+    ExtractText,
+    HashFile,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBJob {
+    pub id: Uuid,
+    pub kind: JobKind,
+    pub note_id: Uuid,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, Clone)]
+pub struct DBUploadSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub total_size: i64,
+    pub received_size: i64,
+    /// The note's details as sent when the upload was started
+    pub metadata: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Whether the upload is being turned into a note
+    pub completing: bool,
+}
@@ -0,0 +1,120 @@
+//! Duplicate detection for uploaded notes.
+//!
+//! Exact duplicates are found by the SHA-256 of the file. Near duplicates, e.g. the same notes
+//! rescanned or exported again, are found by comparing bottom-k sketches of the word shingles
+//! in the PDF's extracted text. Text is extracted with pdfium in a worker process.
+
+use pdfium_render::prelude::*;
+use sha2::{Digest, Sha256};
+use sqlx::Postgres;
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::db::handlers::duplicates::{
+    get_sketch_candidates, insert_note_duplicate, set_note_text_sketch,
+};
+use crate::db::models::DBNoteDuplicate;
+use crate::pdf::{pdfium, PdfError};
+use crate::pdf_worker::{run_pdf_task, PdfTask};
+
+/// Number of consecutive words in a shingle
+const SHINGLE_SIZE: usize = 5;
+/// Number of shingle hashes kept per document
+const SKETCH_SIZE: usize = 128;
+/// Estimated Jaccard similarity above which two notes are flagged as near duplicates
+const NEAR_DUPLICATE_THRESHOLD: f32 = 0.7;
+/// Text extraction stops after this many bytes, which is plenty to sketch a document
+const MAX_TEXT_LENGTH: usize = 1024 * 1024;
+const EXTRACT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Reads the text of every page of a PDF, up to [`MAX_TEXT_LENGTH`]. This runs in a pdfium worker process.
+pub(crate) fn read_pdf_text(pdf_path: &Path) -> Result<String, PdfError> {
+    let pdfium = pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(pdf_path, None)
+        .map_err(|err| PdfError::Invalid(err.to_string()))?;
+
+    let mut text = String::new();
+    for page in document.pages().iter() {
+        let page_text = page.text().map_err(|err| PdfError::Invalid(err.to_string()))?;
+        text.push_str(&page_text.all());
+        text.push('\n');
+        if text.len() >= MAX_TEXT_LENGTH {
+            break;
+        }
+    }
+    Ok(text)
+}
+
+/// Extracts the text of a PDF in a worker process, which is killed if it takes too long.
+pub async fn extract_text(pdf_path: &Path) -> Result<String, PdfError> {
+    let task = PdfTask::ExtractText {
+        path: pdf_path.to_path_buf(),
+    };
+    run_pdf_task(task, EXTRACT_TIMEOUT).await
+}
+
+fn shingle_hash(words: &[String]) -> i64 {
+    let digest = Sha256::digest(words.join(" ").as_bytes());
+    i64::from_be_bytes(digest[..8].try_into().unwrap())
+}
+
+/// Builds the bottom-k sketch of a text: the smallest hashes of its word shingles.
+///
+/// Returns `None` for texts too short to compare meaningfully, e.g. scanned PDFs without a text layer.
+pub fn text_sketch(text: &str) -> Option<Vec<i64>> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.len() < SHINGLE_SIZE * 4 {
+        return None;
+    }
+
+    let hashes: BTreeSet<i64> = words.windows(SHINGLE_SIZE).map(shingle_hash).collect();
+    Some(hashes.into_iter().take(SKETCH_SIZE).collect())
+}
+
+/// Estimates the Jaccard similarity of two documents from their sketches.
+pub fn sketch_similarity(a: &[i64], b: &[i64]) -> f32 {
+    let a: BTreeSet<i64> = a.iter().copied().collect();
+    let b: BTreeSet<i64> = b.iter().copied().collect();
+    let union_sketch: Vec<i64> = a.union(&b).copied().take(SKETCH_SIZE).collect();
+    if union_sketch.is_empty() {
+        return 0.0;
+    }
+
+    let shared = union_sketch
+        .iter()
+        .filter(|hash| a.contains(hash) && b.contains(hash))
+        .count();
+    shared as f32 / union_sketch.len() as f32
+}
+
+/// Sketches the extracted text of a newly uploaded note and flags the notes it nearly duplicates.
+///
+/// PDFs without enough text to compare are skipped.
+pub async fn flag_near_duplicates(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    note_id: Uuid,
+    text: &str,
+) -> Result<Vec<DBNoteDuplicate>, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(sketch) = text_sketch(text) else {
+        return Ok(Vec::new());
+    };
+
+    set_note_text_sketch(tx, note_id, &sketch).await?;
+
+    let mut duplicates = Vec::new();
+    for (candidate_id, candidate_sketch) in get_sketch_candidates(tx, note_id, &sketch).await? {
+        let similarity = sketch_similarity(&sketch, &candidate_sketch);
+        if similarity >= NEAR_DUPLICATE_THRESHOLD {
+            duplicates.push(insert_note_duplicate(tx, note_id, candidate_id, similarity).await?);
+        }
+    }
+
+    Ok(duplicates)
+}
@@ -1,6 +1,10 @@
 use std::path::PathBuf;
 use clap::Parser;
+use crate::commands::Command;
 use crate::pathutils::Paths;
+use crate::scan::{ClamdScanner, ScannerBackend};
+use crate::storage::{LocalStorage, S3Storage, StorageBackend, StorageKind};
+use url::Url;
 
 #[derive(Parser, Clone)]
 #[clap(name = "")]
@@ -42,15 +46,75 @@ pub struct EnvVars {
     #[arg(env, default_value = "previews/uploaded")]
     /// The path where uploaded notes are stored temporarily, relative to the `static_file_storage_location`
     previews_path: PathBuf,
+    #[arg(env, default_value = "quarantine")]
+    /// The path where files taken out of service are moved to, relative to the `static_file_storage_location`
+    quarantine_path: PathBuf,
+    #[arg(env, default_value = "3600")]
+    /// How long the signed file URLs in API responses stay valid
+    pub file_url_expiration_seconds: u64,
+    #[arg(env, default_value = "http://localhost:8085")]
+    /// The public URL of this server, signed file URLs point to it
+    pub public_api_url: String,
 
 
+    // Storage
+    #[arg(env, value_enum, default_value = "local")]
+    /// Where note files and previews are stored: on the local disk, or in an S3-compatible bucket
+    storage_backend: StorageKind,
+    #[arg(env)]
+    /// The S3 endpoint URL, e.g. `http://localhost:9000` for a local MinIO
+    s3_endpoint: Option<String>,
+    #[arg(env)]
+    /// The bucket note files and previews are stored in
+    s3_bucket: Option<String>,
+    #[arg(env, default_value = "us-east-1")]
+    /// The region requests are signed for
+    s3_region: String,
+    #[arg(env)]
+    s3_access_key_id: Option<String>,
+    #[arg(env)]
+    s3_secret_access_key: Option<String>,
+
+    // PDF processing
+    #[arg(env)]
+    /// The directory containing the pdfium library. The system library path is searched if not set.
+    pub pdfium_library_path: Option<PathBuf>,
+    #[arg(env, default_value = "2")]
+    /// Number of workers processing background jobs, such as rendering previews
+    pub job_workers: usize,
+
+    // Malware scanning
+    #[arg(env)]
+    /// Path of clamd's Unix socket, or `host:port` to reach it over TCP. Uploads aren't scanned if not set.
+    clamd_address: Option<String>,
+    #[arg(env)]
+    /// Webhook moderator alerts are posted to, e.g. a Slack incoming webhook
+    pub moderator_webhook_url: Option<String>,
+
+    #[arg(env, default_value = "1")]
+    /// Number of reverse proxies in front of the server that append the client's address to
+    /// `X-Forwarded-For`. 0 ignores the header and uses the socket address.
+    pub trusted_proxy_hops: usize,
+
     #[arg(env, default_value = "/app/log")]
     /// Location where logs are stored
     pub log_location: PathBuf,
 
+    #[command(subcommand)]
+    /// Maintenance command to run instead of starting the server
+    pub command: Option<Command>,
+
     #[arg(skip)]
     /// All paths must be handled using this
     pub paths: Paths,
+
+    #[arg(skip)]
+    /// Storage for note files and previews, picked by `storage_backend`
+    pub storage: StorageBackend,
+
+    #[arg(skip)]
+    /// Malware scanner for uploads, clamd if `clamd_address` is set
+    pub scanner: ScannerBackend,
 }
 
 impl EnvVars {
@@ -61,10 +125,37 @@ impl EnvVars {
             &self.static_file_storage_location,
             &self.uploaded_notes_path,
             &self.previews_path,
+            &self.quarantine_path,
         )?;
 
         self.log_location = std::path::absolute(self.log_location)?;
 
+        self.storage = match self.storage_backend {
+            StorageKind::Local => StorageBackend::Local(LocalStorage::new(
+                std::path::absolute(&self.static_file_storage_location)?,
+                Url::parse(&self.static_files_url)?,
+            )),
+            StorageKind::S3 => {
+                let missing = |name: &str| color_eyre::eyre::eyre!("{} must be set to use S3 storage", name);
+                StorageBackend::S3(S3Storage::new(
+                    Url::parse(self.s3_endpoint.as_deref().ok_or_else(|| missing("S3_ENDPOINT"))?)?,
+                    self.s3_bucket.clone().ok_or_else(|| missing("S3_BUCKET"))?,
+                    self.s3_region.clone(),
+                    self.s3_access_key_id.clone().ok_or_else(|| missing("S3_ACCESS_KEY_ID"))?,
+                    self.s3_secret_access_key.clone().ok_or_else(|| missing("S3_SECRET_ACCESS_KEY"))?,
+                ))
+            }
+        };
+
+        // Left empty in .env to disable them
+        self.clamd_address = self.clamd_address.filter(|address| !address.trim().is_empty());
+        self.moderator_webhook_url = self.moderator_webhook_url.filter(|url| !url.trim().is_empty());
+
+        self.scanner = match &self.clamd_address {
+            Some(address) => ScannerBackend::Clamd(ClamdScanner::new(address.clone())),
+            None => ScannerBackend::Disabled,
+        };
+
         Ok(self)
     }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+//! Consistency checks between stored files and the database.
+//!
+//! Files and note rows can drift apart: uploads put the PDF into storage before the note row is
+//! committed, and failure paths only delete files on a best-effort basis. The check finds files
+//! that don't belong to any note, notes whose PDF is missing and notes whose `has_preview_image`
+//! flag doesn't match the preview files in storage. When fixing, orphaned files are moved to the
+//! quarantine directory instead of being deleted, and preview flags are set to match the files.
+
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::db::handlers::notes::{get_note_preview_statuses, update_note_preview_status};
+use crate::db::DBPoolWrapper;
+use crate::pathutils::Paths;
+use crate::preview::{preview_file_names, PREVIEW_PAGES};
+use crate::storage::{Storage, StorageBackend, StorageError};
+use crate::upload::TempUpload;
+
+#[derive(Debug)]
+pub struct PreviewMismatch {
+    pub note_id: Uuid,
+    pub has_preview_image: bool,
+    pub preview_pages: i32,
+    /// Number of pages whose preview files are all in storage
+    pub found_pages: i32,
+}
+
+#[derive(Debug)]
+pub struct FsckReport {
+    pub checked_notes: usize,
+    pub checked_files: usize,
+    /// Keys of files that don't belong to any note
+    pub orphaned_files: Vec<String>,
+    /// Notes whose PDF isn't in storage
+    pub missing_pdfs: Vec<Uuid>,
+    pub preview_mismatches: Vec<PreviewMismatch>,
+    pub quarantined: usize,
+    pub corrected: usize,
+    /// Problems that couldn't be fixed, with the reason
+    pub failed: Vec<String>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_files.is_empty() && self.missing_pdfs.is_empty() && self.preview_mismatches.is_empty()
+    }
+}
+
+/// Lists the files under a directory in storage, as names relative to it. Temporary files of
+/// uploads and renders in progress are hidden and skipped.
+async fn list_files(storage: &StorageBackend, dir: &str) -> Result<Vec<(String, String)>, StorageError> {
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    let files = storage
+        .list(&prefix)
+        .await?
+        .into_iter()
+        .filter_map(|key| {
+            let name = key.strip_prefix(&prefix)?.to_string();
+            (!name.split('/').any(|segment| segment.starts_with('.'))).then_some((key, name))
+        })
+        .collect();
+    Ok(files)
+}
+
+/// The note a preview file belongs to, from a name like `{id}.jpg` or `{id}/{page}-{size}.{ext}`.
+fn preview_note_id(name: &str) -> Option<Uuid> {
+    match name.split_once('/') {
+        Some((id, _)) => id.parse().ok(),
+        None => name.strip_suffix(".jpg")?.parse().ok(),
+    }
+}
+
+/// Moves a stored file to the quarantine directory.
+async fn quarantine_file(storage: &StorageBackend, paths: &Paths, key: &str) -> Result<(), StorageError> {
+    let file = TempUpload::from_storage(storage, key, paths.get_notes_dir()).await?;
+    file.persist(storage, &paths.get_quarantine_slug(key)).await?;
+    storage.delete(key).await
+}
+
+/// Checks stored files against the notes in the database, fixing what can be fixed if `fix` is set.
+///
+/// Files are listed before notes are read, so notes committed during the check aren't reported as
+/// orphans, but a file whose upload is still being committed can be. Run it with `fix` while no
+/// uploads are happening.
+pub async fn check_storage(
+    db_wrapper: &DBPoolWrapper,
+    storage: &StorageBackend,
+    paths: &Paths,
+    fix: bool,
+) -> Result<FsckReport, Box<dyn std::error::Error>> {
+    let note_files = list_files(storage, &paths.get_note_slug("")).await?;
+    let preview_files = list_files(storage, &paths.get_preview_slug("")).await?;
+    let statuses = get_note_preview_statuses(db_wrapper).await?;
+
+    let note_ids = statuses.iter().map(|status| status.id).collect::<HashSet<_>>();
+    let mut report = FsckReport {
+        checked_notes: statuses.len(),
+        checked_files: note_files.len() + preview_files.len(),
+        orphaned_files: Vec::new(),
+        missing_pdfs: Vec::new(),
+        preview_mismatches: Vec::new(),
+        quarantined: 0,
+        corrected: 0,
+        failed: Vec::new(),
+    };
+
+    let mut pdfs = HashSet::new();
+    for (key, name) in note_files {
+        match name.strip_suffix(".pdf").and_then(|id| id.parse::<Uuid>().ok()) {
+            Some(note_id) if note_ids.contains(&note_id) => {
+                pdfs.insert(note_id);
+            }
+            _ => report.orphaned_files.push(key),
+        }
+    }
+
+    let mut previews: HashMap<Uuid, HashSet<String>> = HashMap::new();
+    for (key, name) in preview_files {
+        match preview_note_id(&name) {
+            Some(note_id) if note_ids.contains(&note_id) => {
+                previews.entry(note_id).or_default().insert(name);
+            }
+            _ => report.orphaned_files.push(key),
+        }
+    }
+
+    for status in &statuses {
+        if !pdfs.contains(&status.id) {
+            report.missing_pdfs.push(status.id);
+        }
+
+        let files = previews.remove(&status.id).unwrap_or_default();
+        let complete = |pages: i32| preview_file_names(status.id, pages).iter().all(|name| files.contains(name));
+        let found_pages = (1..=PREVIEW_PAGES as i32)
+            .take_while(|pages| complete(*pages))
+            .last()
+            .unwrap_or(0);
+
+        let mismatched = if status.has_preview_image {
+            !complete(status.preview_pages)
+        } else {
+            found_pages > 0
+        };
+        if mismatched {
+            report.preview_mismatches.push(PreviewMismatch {
+                note_id: status.id,
+                has_preview_image: status.has_preview_image,
+                preview_pages: status.preview_pages,
+                found_pages,
+            });
+        }
+    }
+
+    if !fix {
+        return Ok(report);
+    }
+
+    for key in &report.orphaned_files {
+        match quarantine_file(storage, paths, key).await {
+            Ok(()) => report.quarantined += 1,
+            Err(err) => report.failed.push(format!("Failed to quarantine {}: {}", key, err)),
+        }
+    }
+
+    for mismatch in &report.preview_mismatches {
+        let has_preview_image = mismatch.found_pages > 0;
+        match update_note_preview_status(db_wrapper, mismatch.note_id, has_preview_image, mismatch.found_pages).await {
+            Ok(()) => report.corrected += 1,
+            Err(err) => report
+                .failed
+                .push(format!("Failed to correct the preview flag of {}: {}", mismatch.note_id, err)),
+        }
+    }
+
+    Ok(report)
+}
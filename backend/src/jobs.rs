@@ -0,0 +1,127 @@
+//! Background processing of uploaded notes.
+//!
+//! Rendering previews, extracting text for near duplicate detection and hashing files are queued
+//! as jobs in Postgres instead of running during the upload. Workers claim jobs with
+//! `FOR UPDATE SKIP LOCKED`, so several workers (and server instances) can share the queue, and
+//! failed jobs are retried with exponential backoff until they run out of attempts.
+
+use chrono::{Duration, Utc};
+use std::error::Error;
+
+use crate::db::handlers::duplicates::{get_note_id_by_file_hash, insert_note_duplicate, set_note_file_sha256};
+use crate::db::handlers::jobs::{claim_job, complete_job, fail_job};
+use crate::db::handlers::notes::update_note_preview_status;
+use crate::db::models::{DBJob, JobKind};
+use crate::db::DBPoolWrapper;
+use crate::dedup::{extract_text, flag_near_duplicates};
+use crate::pathutils::Paths;
+use crate::preview::generate_previews;
+use crate::storage::StorageBackend;
+use crate::upload::TempUpload;
+
+/// How long an idle worker waits before checking the queue again
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Jobs running for longer than this are assumed to belong to a worker that died, and are claimed again
+const LOCK_TIMEOUT_SECONDS: f64 = 600.0;
+/// Delay before the first retry, doubled for every further attempt
+const RETRY_BASE_DELAY_SECONDS: i64 = 30;
+const MAX_RETRY_DELAY_SECONDS: i64 = 60 * 60;
+
+type JobError = Box<dyn Error + Send + Sync>;
+
+/// Starts `count` workers processing the job queue.
+pub fn spawn_workers(db_wrapper: DBPoolWrapper, storage: StorageBackend, paths: Paths, count: usize) {
+    for _ in 0..count {
+        tokio::spawn(run_worker(db_wrapper.clone(), storage.clone(), paths.clone()));
+    }
+}
+
+async fn run_worker(db_wrapper: DBPoolWrapper, storage: StorageBackend, paths: Paths) {
+    loop {
+        let job = match claim_job(&db_wrapper, LOCK_TIMEOUT_SECONDS).await {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+            Err(err) => {
+                tracing::error!("Failed to claim job: {}", err);
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+        };
+
+        let result = match run_job(&db_wrapper, &storage, &paths, &job).await {
+            Ok(()) => complete_job(&db_wrapper, job.id).await,
+            Err(err) => {
+                let retry_at = (job.attempts < job.max_attempts).then(|| Utc::now() + retry_delay(job.attempts));
+                match retry_at {
+                    Some(_) => tracing::warn!(
+                        "{:?} job {} for note {} failed on attempt {}: {}",
+                        job.kind, job.id, job.note_id, job.attempts, err
+                    ),
+                    None => tracing::error!(
+                        "{:?} job {} for note {} failed for good after {} attempts: {}",
+                        job.kind, job.id, job.note_id, job.attempts, err
+                    ),
+                }
+                fail_job(&db_wrapper, job.id, &err.to_string(), retry_at).await
+            }
+        };
+
+        if let Err(err) = result {
+            tracing::error!("Failed to update job {}: {}", job.id, err);
+        }
+    }
+}
+
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::seconds((RETRY_BASE_DELAY_SECONDS << exponent).min(MAX_RETRY_DELAY_SECONDS))
+}
+
+async fn run_job(
+    db_wrapper: &DBPoolWrapper,
+    storage: &StorageBackend,
+    paths: &Paths,
+    job: &DBJob,
+) -> Result<(), JobError> {
+    // Every job works on a local copy of the note's PDF
+    let pdf_key = paths.get_note_slug(&format!("{}.pdf", job.note_id));
+    let pdf = TempUpload::from_storage(storage, &pdf_key, paths.get_notes_dir()).await?;
+
+    match job.kind {
+        JobKind::RenderPreview => {
+            let preview_pages = generate_previews(storage, paths, job.note_id, pdf.path()).await?;
+            update_note_preview_status(db_wrapper, job.note_id, true, preview_pages).await?;
+        }
+        JobKind::ExtractText => {
+            // Extraction can take a while, so it happens before the transaction is opened
+            let text = extract_text(pdf.path()).await?;
+            let mut tx = db_wrapper.pool().begin().await?;
+            let duplicates = flag_near_duplicates(&mut tx, job.note_id, &text).await?;
+            tx.commit().await?;
+            if !duplicates.is_empty() {
+                tracing::info!(
+                    "Note {} flagged as a near duplicate of {} notes",
+                    job.note_id,
+                    duplicates.len()
+                );
+            }
+        }
+        JobKind::HashFile => {
+            match get_note_id_by_file_hash(db_wrapper, &pdf.sha256).await? {
+                // Hashes are unique, so an exact copy of an existing note is flagged for moderators instead
+                Some(original_id) if original_id != job.note_id => {
+                    let mut tx = db_wrapper.pool().begin().await?;
+                    insert_note_duplicate(&mut tx, job.note_id, original_id, 1.0).await?;
+                    tx.commit().await?;
+                }
+                Some(_) => (),
+                None => set_note_file_sha256(db_wrapper, job.note_id, &pdf.sha256).await?,
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,8 +1,22 @@
 // backend/src/main.rs
+mod alerts;
 mod api;
+mod cache;
+mod catalog;
+mod commands;
+mod convert;
 mod db;
+mod dedup;
 mod env;
+mod fsck;
+mod jobs;
 mod pathutils;
+mod pdf;
+mod pdf_worker;
+mod preview;
+mod scan;
+mod storage;
+mod upload;
 
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::limit::RequestBodyLimitLayer;
@@ -18,6 +32,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     dotenv().ok();
     let env_vars = env::EnvVars::parse().process()?;
 
+    // Workers reply on stdout, so they run before logging is set up
+    if let Some(commands::Command::PdfWorker) = env_vars.command {
+        return pdf_worker::run_worker(env_vars.pdfium_library_path.as_deref());
+    }
+
     let (append_writer, _guard) = tracing_appender::non_blocking(tracing_appender::rolling::never(
         env_vars
             .log_location
@@ -43,6 +62,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_wrapper = db::DBPoolWrapper::new(env_vars.clone()).await;
     tracing::info!("Database connection established.");
 
+    if let Some(command) = env_vars.command.clone() {
+        return commands::run(command, &env_vars, &db_wrapper).await;
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods([
@@ -61,6 +84,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             HeaderName::from_static("x-requested-with"),
         ]);
 
+    tokio::spawn(upload::cleanup_expired_sessions(
+        db_wrapper.clone(),
+        env_vars.paths.get_notes_dir().to_path_buf(),
+    ));
+    jobs::spawn_workers(
+        db_wrapper.clone(),
+        env_vars.storage.clone(),
+        env_vars.paths.clone(),
+        env_vars.job_workers,
+    );
+
     let file_size_limit = env_vars.file_size_limit;
     let port = env_vars.port;
     
@@ -73,7 +107,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-/// Derived from https://github.com/metakgp/iqps-go
+//! Derived from https://github.com/metakgp/iqps-go
 
 use std::{
     fs,
@@ -11,8 +11,10 @@ use url::Url;
 /// Struct containing all the paths and URLs required to parse or create any note's slug, absolute path, or URL.
 pub struct Paths {
     /// URL of the static files server
+    #[allow(dead_code)]
     static_files_url: Url,
     /// The absolute path to the location from where the static files server serves files
+    #[allow(dead_code)]
     static_files_path: PathBuf,
     /// The absolute system path to the notes directory on the server
     notes_system_path: PathBuf,
@@ -22,7 +24,10 @@ pub struct Paths {
     previews_system_path: PathBuf,
     /// The slug to the previews directory
     previews_path_slug: PathBuf,
+    /// The slug to the directory files taken out of service are moved to
+    quarantine_path_slug: PathBuf,
 
+    #[allow(dead_code)]
     log_location: PathBuf,
 }
 
@@ -32,6 +37,7 @@ impl Default for Paths {
         let static_file_storage_location = PathBuf::from("./static_files");
         let notes_path_slug = PathBuf::from("notes/uploaded");
         let previews_path_slug = PathBuf::from("notes/previews");
+        let quarantine_path_slug = PathBuf::from("quarantine");
         let log_location = PathBuf::from("./logs");
 
         Self {
@@ -42,6 +48,7 @@ impl Default for Paths {
             notes_path_slug,
             previews_system_path: static_file_storage_location.join(&previews_path_slug),
             previews_path_slug,
+            quarantine_path_slug,
             log_location,
         }
     }
@@ -56,11 +63,13 @@ impl Paths {
     /// * `static_file_storage_location` - The absolute path from which static files are served.
     /// * `notes_relative_path` - The path for notes, relative to the static storage location (e.g., "notes/uploaded").
     /// * `previews_relative_path` - The path for previews, relative to the static storage location (e.g., "notes/previews").
+    /// * `quarantine_relative_path` - The path for quarantined files, relative to the static storage location (e.g., "quarantine").
     pub fn new(
         static_files_url: &str,
         static_file_storage_location: &Path,
         notes_relative_path: &Path,
         previews_relative_path: &Path,
+        quarantine_relative_path: &Path,
     ) -> Result<Self, color_eyre::eyre::Error> {
         let static_files_abs_path = path::absolute(static_file_storage_location)?;
 
@@ -88,6 +97,7 @@ impl Paths {
             notes_path_slug,
             previews_system_path,
             previews_path_slug,
+            quarantine_path_slug: quarantine_relative_path.to_owned(),
             log_location,
         })
     }
@@ -101,15 +111,6 @@ impl Paths {
             .to_string()
     }
 
-    pub fn get_note_path(&self, filename: &str) -> PathBuf {
-        self.notes_system_path.join(filename)
-    }
-
-    pub fn get_note_url(&self, filename: &str) -> Result<String, color_eyre::eyre::Error> {
-        let slug = self.get_note_slug(filename);
-        self.get_url_from_slug(&slug)
-    }
-
     pub fn get_notes_dir(&self) -> &Path {
         &self.notes_system_path
     }
@@ -123,22 +124,17 @@ impl Paths {
             .to_string()
     }
 
-    pub fn get_preview_path(&self, filename: &str) -> PathBuf {
-        self.previews_system_path.join(filename)
-    }
-
-    pub fn get_preview_url(&self, filename: &str) -> Result<String, color_eyre::eyre::Error> {
-        let slug = self.get_preview_slug(filename);
-        self.get_url_from_slug(&slug)
-    }
-
     pub fn get_previews_dir(&self) -> &Path {
         &self.previews_system_path
     }
 
+    // --- Quarantine Functions ---
 
-    pub fn get_url_from_slug(&self, slug: &str) -> Result<String, color_eyre::eyre::Error> {
-        Ok(self.static_files_url.join(slug)?.as_str().to_string())
+    /// Slug a stored file is moved to when it is quarantined, keeping its original key for reference.
+    pub fn get_quarantine_slug(&self, key: &str) -> String {
+        self.quarantine_path_slug
+            .join(key)
+            .to_string_lossy()
+            .to_string()
     }
-
-}
\ No newline at end of file
+}
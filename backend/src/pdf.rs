@@ -0,0 +1,181 @@
+//! Validation of uploaded PDFs with pdfium.
+//!
+//! The client's content type can't be trusted, so uploaded files are checked for the PDF header
+//! and then opened with pdfium to make sure they actually parse. The page count and document
+//! metadata are read while the file is open.
+//!
+//! Pdfium only runs in worker processes, see [`crate::pdf_worker`], so the functions here that
+//! use it are called by workers and the rest of the server goes through [`inspect_pdf`].
+
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+
+use crate::pdf_worker::{run_pdf_task, PdfTask};
+
+/// Every PDF starts with this header, followed by the version
+const PDF_MAGIC: &[u8] = b"%PDF-";
+/// PDFs with more pages are rejected, no real set of notes comes close
+const MAX_PDF_PAGES: i32 = 2000;
+/// How long opening and reading a PDF may take
+const INSPECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pdfium itself isn't thread-safe, so all use of it goes through this lock
+static PDFIUM: OnceLock<Mutex<Pdfium>> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum PdfError {
+    /// The file doesn't start with the PDF header
+    NotPdf,
+    /// The PDF is encrypted or needs a password to open
+    Encrypted,
+    /// The file has the PDF header but pdfium couldn't parse it
+    Invalid(String),
+    /// The pdfium library couldn't be loaded
+    Unavailable,
+    /// Processing took too long and the worker was killed
+    TimedOut,
+    /// Too many PDFs are being processed to start another
+    Busy,
+    /// The worker process crashed or failed for another reason
+    Worker(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for PdfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PdfError::NotPdf => write!(f, "File is not a PDF"),
+            PdfError::Encrypted => write!(f, "PDF is encrypted"),
+            PdfError::Invalid(msg) => write!(f, "Failed to parse PDF: {}", msg),
+            PdfError::Unavailable => write!(f, "The pdfium library is not loaded"),
+            PdfError::TimedOut => write!(f, "Processing the PDF took too long"),
+            PdfError::Busy => write!(f, "Too many PDFs are being processed"),
+            PdfError::Worker(msg) => write!(f, "Failed to process PDF: {}", msg),
+            PdfError::Io(err) => write!(f, "Failed to read PDF: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PdfError {}
+
+impl From<std::io::Error> for PdfError {
+    fn from(err: std::io::Error) -> Self {
+        PdfError::Io(err)
+    }
+}
+
+/// The document information dictionary of a PDF.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct PdfDocumentInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub creator: Option<String>,
+    pub producer: Option<String>,
+    pub creation_date: Option<String>,
+    pub modification_date: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PdfInfo {
+    pub page_count: i32,
+    pub metadata: PdfDocumentInfo,
+}
+
+/// Loads the pdfium library from `library_dir`, or from the system library path if not given.
+///
+/// Failing to load it is logged rather than fatal, uploads are rejected until it is available.
+pub fn init_pdfium(library_dir: Option<&Path>) {
+    let bindings = match library_dir {
+        Some(dir) => Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(dir)),
+        None => Pdfium::bind_to_system_library(),
+    };
+
+    match bindings {
+        Ok(bindings) => {
+            let _ = PDFIUM.set(Mutex::new(Pdfium::new(bindings)));
+            tracing::info!("Loaded pdfium library.");
+        }
+        Err(err) => tracing::error!("Failed to load pdfium library: {}", err),
+    }
+}
+
+/// Locks the pdfium library, which is only loaded in worker processes.
+pub fn pdfium() -> Result<MutexGuard<'static, Pdfium>, PdfError> {
+    let pdfium = PDFIUM.get().ok_or(PdfError::Unavailable)?;
+    // A panic while rendering doesn't leave pdfium itself in a bad state
+    Ok(pdfium.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+/// Checks that the file starts with the PDF header.
+async fn check_pdf_magic(path: &Path) -> Result<(), PdfError> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut header = [0; PDF_MAGIC.len()];
+    match file.read_exact(&mut header).await {
+        Ok(_) if header == PDF_MAGIC => Ok(()),
+        Ok(_) => Err(PdfError::NotPdf),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Err(PdfError::NotPdf),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub(crate) fn read_pdf_info(path: &Path) -> Result<PdfInfo, PdfError> {
+    let pdfium = pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(path, None)
+        .map_err(|err| match err {
+            PdfiumError::PdfiumLibraryInternalError(
+                PdfiumInternalError::PasswordError | PdfiumInternalError::SecurityError,
+            ) => PdfError::Encrypted,
+            err => PdfError::Invalid(err.to_string()),
+        })?;
+
+    // Documents with only an owner password open without one, but are still encrypted
+    if !matches!(
+        document.permissions().security_handler_revision(),
+        Ok(PdfSecurityHandlerRevision::Unprotected)
+    ) {
+        return Err(PdfError::Encrypted);
+    }
+
+    let page_count = document.pages().len() as i32;
+    if page_count == 0 {
+        return Err(PdfError::Invalid("PDF has no pages".to_string()));
+    }
+    if page_count > MAX_PDF_PAGES {
+        return Err(PdfError::Invalid(format!("PDF has more than {} pages", MAX_PDF_PAGES)));
+    }
+
+    let metadata = document.metadata();
+    let tag = |tag_type| {
+        metadata
+            .get(tag_type)
+            .map(|tag| tag.value().trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    Ok(PdfInfo {
+        page_count,
+        metadata: PdfDocumentInfo {
+            title: tag(PdfDocumentMetadataTagType::Title),
+            author: tag(PdfDocumentMetadataTagType::Author),
+            subject: tag(PdfDocumentMetadataTagType::Subject),
+            keywords: tag(PdfDocumentMetadataTagType::Keywords),
+            creator: tag(PdfDocumentMetadataTagType::Creator),
+            producer: tag(PdfDocumentMetadataTagType::Producer),
+            creation_date: tag(PdfDocumentMetadataTagType::CreationDate),
+            modification_date: tag(PdfDocumentMetadataTagType::ModificationDate),
+        },
+    })
+}
+
+/// Validates an uploaded PDF and reads its page count and metadata.
+pub async fn inspect_pdf(path: PathBuf) -> Result<PdfInfo, PdfError> {
+    check_pdf_magic(&path).await?;
+    run_pdf_task(PdfTask::Inspect { path }, INSPECT_TIMEOUT).await
+}
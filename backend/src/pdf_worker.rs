@@ -0,0 +1,206 @@
+//! Pdfium work in separate processes that can be killed.
+//!
+//! Pdfium can't be interrupted once it starts on a document, and a crafted PDF can make it loop or
+//! allocate without bound. So the server never calls pdfium itself: each task runs in a short-lived
+//! worker, which is this binary started again with the hidden `pdf-worker` command. The task is
+//! written to the worker's stdin as JSON, and the result is read back from its stdout.
+//!
+//! Workers are killed when they take longer than the caller's timeout, and their memory and CPU
+//! time are capped with rlimits, so a malicious file only takes down its own worker. At most
+//! [`MAX_WORKERS`] run at once, callers that don't get a slot in time get [`PdfError::Busy`]. The
+//! other conversion tools, like LibreOffice, take slots from the same pool.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use uuid::Uuid;
+
+use crate::convert::{write_images_pdf, ConvertError};
+use crate::dedup::read_pdf_text;
+use crate::pdf::{init_pdfium, read_pdf_info, PdfError};
+use crate::preview::{render_previews_blocking, PreviewError};
+
+/// Most workers and conversion tools running at the same time
+const MAX_WORKERS: usize = 4;
+/// How long to wait for a free worker slot before giving up
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Memory a worker may allocate, in bytes
+const WORKER_MEMORY_LIMIT: u64 = 1024 * 1024 * 1024;
+
+static WORKER_SLOTS: Semaphore = Semaphore::const_new(MAX_WORKERS);
+
+/// Work done by a worker. Paths are of local files, relative paths are resolved against the
+/// server's working directory, which workers inherit.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "task", rename_all = "snake_case")]
+pub enum PdfTask {
+    /// Validates a PDF and reads its page count and metadata
+    Inspect { path: PathBuf },
+    /// Renders the previews of a note into `previews_dir`, replying with the number of pages rendered
+    RenderPreviews {
+        pdf_path: PathBuf,
+        note_id: Uuid,
+        previews_dir: PathBuf,
+    },
+    /// Merges images into a PDF with one page per image
+    ImagesToPdf { images: Vec<PathBuf>, output: PathBuf },
+    /// Extracts the text of a PDF
+    ExtractText { path: PathBuf },
+}
+
+/// A failed task, as sent back by the worker.
+#[derive(Serialize, Deserialize, Debug)]
+enum TaskError {
+    NotPdf,
+    Encrypted,
+    Invalid(String),
+    Unavailable,
+    Failed(String),
+}
+
+impl From<PdfError> for TaskError {
+    fn from(err: PdfError) -> Self {
+        match err {
+            PdfError::NotPdf => TaskError::NotPdf,
+            PdfError::Encrypted => TaskError::Encrypted,
+            PdfError::Invalid(msg) => TaskError::Invalid(msg),
+            PdfError::Unavailable => TaskError::Unavailable,
+            err => TaskError::Failed(err.to_string()),
+        }
+    }
+}
+
+impl From<PreviewError> for TaskError {
+    fn from(err: PreviewError) -> Self {
+        match err {
+            PreviewError::Pdf(err) => err.into(),
+            err => TaskError::Failed(err.to_string()),
+        }
+    }
+}
+
+impl From<ConvertError> for TaskError {
+    fn from(err: ConvertError) -> Self {
+        match err {
+            ConvertError::Failed(msg) => TaskError::Failed(msg),
+            ConvertError::Unavailable(_) => TaskError::Unavailable,
+            err => TaskError::Failed(err.to_string()),
+        }
+    }
+}
+
+impl From<TaskError> for PdfError {
+    fn from(err: TaskError) -> Self {
+        match err {
+            TaskError::NotPdf => PdfError::NotPdf,
+            TaskError::Encrypted => PdfError::Encrypted,
+            TaskError::Invalid(msg) => PdfError::Invalid(msg),
+            TaskError::Unavailable => PdfError::Unavailable,
+            TaskError::Failed(msg) => PdfError::Worker(msg),
+        }
+    }
+}
+
+fn reply<T: Serialize, E: Into<TaskError>>(result: Result<T, E>) -> Result<serde_json::Value, TaskError> {
+    let value = result.map_err(Into::into)?;
+    serde_json::to_value(value).map_err(|err| TaskError::Failed(err.to_string()))
+}
+
+/// Runs the `pdf-worker` command: reads a task from stdin, runs it and writes the result to stdout.
+pub fn run_worker(library_dir: Option<&Path>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    let task: PdfTask = serde_json::from_slice(&input)?;
+
+    init_pdfium(library_dir);
+    let result = match task {
+        PdfTask::Inspect { path } => reply(read_pdf_info(&path)),
+        PdfTask::RenderPreviews {
+            pdf_path,
+            note_id,
+            previews_dir,
+        } => reply(render_previews_blocking(&pdf_path, note_id, &previews_dir)),
+        PdfTask::ImagesToPdf { images, output } => reply(write_images_pdf(&images, &output)),
+        PdfTask::ExtractText { path } => reply(read_pdf_text(&path)),
+    };
+
+    serde_json::to_writer(std::io::stdout().lock(), &result)?;
+    Ok(())
+}
+
+/// Caps the memory and CPU time of a worker. Runs in the forked child before it execs.
+fn limit_resources(cpu_seconds: u64) -> std::io::Result<()> {
+    let limits = [
+        (libc::RLIMIT_DATA, WORKER_MEMORY_LIMIT),
+        (libc::RLIMIT_CPU, cpu_seconds),
+    ];
+    for (resource, value) in limits {
+        let limit = libc::rlimit {
+            rlim_cur: value,
+            rlim_max: value,
+        };
+        // SAFETY: setrlimit only reads the struct it's given
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Waits for a free slot to run a worker or another heavy process in, which is held until the
+/// permit is dropped.
+pub async fn acquire_worker_slot() -> Result<SemaphorePermit<'static>, PdfError> {
+    tokio::time::timeout(QUEUE_TIMEOUT, WORKER_SLOTS.acquire())
+        .await
+        .map_err(|_| PdfError::Busy)?
+        .map_err(|_| PdfError::Busy)
+}
+
+/// Runs a task in a worker process, killing it if it takes longer than `timeout`.
+pub async fn run_pdf_task<T: DeserializeOwned>(task: PdfTask, timeout: Duration) -> Result<T, PdfError> {
+    let _slot = acquire_worker_slot().await?;
+
+    let input = serde_json::to_vec(&task).map_err(|err| PdfError::Worker(err.to_string()))?;
+    let cpu_seconds = timeout.as_secs() + 1;
+
+    let mut command = Command::new(std::env::current_exe()?);
+    command
+        .arg("pdf-worker")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // SAFETY: setrlimit is async-signal-safe, and limit_resources doesn't allocate
+    unsafe {
+        command.pre_exec(move || limit_resources(cpu_seconds));
+    }
+    let mut child = command.spawn()?;
+    let mut stdin = child.stdin.take().expect("worker stdin is piped");
+
+    // Dropping the child when the timeout elapses kills it
+    let output = tokio::time::timeout(timeout, async move {
+        stdin.write_all(&input).await?;
+        drop(stdin);
+        child.wait_with_output().await
+    })
+    .await
+    .map_err(|_| PdfError::TimedOut)??;
+
+    if !output.status.success() {
+        return Err(PdfError::Worker(format!(
+            "Worker failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    serde_json::from_slice::<Result<T, TaskError>>(&output.stdout)
+        .map_err(|err| PdfError::Worker(format!("Invalid reply from worker: {}", err)))?
+        .map_err(PdfError::from)
+}
@@ -0,0 +1,416 @@
+//! Preview images of uploaded notes, rendered with pdfium.
+//!
+//! The first few pages of each note are rendered at several sizes, and each image is saved as
+//! AVIF and WebP with a JPEG fallback, so that clients can show a strip of pages to skim before
+//! downloading. Page 1 at the medium size is also saved as `{note_id}.jpg`, the preview image
+//! used before the page strip existed.
+//!
+//! Rendering runs in a pdfium worker process. The rendered bitmap's size is capped so that
+//! PDFs with huge or oddly shaped pages can't use much memory, pages with too many objects aren't
+//! rendered, and workers that take too long are killed so that the job workers aren't held up by
+//! malicious PDFs. Previews are written to a scratch directory and then moved into storage.
+//!
+//! Notes whose previews failed to render, or whose preview files have gone missing, can be
+//! rendered again with [`backfill_previews`].
+
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageEncoder, RgbImage};
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::BufWriter;
+use std::path::Path;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use crate::db::handlers::notes::{get_note_preview_statuses, update_note_preview_status};
+use crate::db::models::NotePreviewStatus;
+use crate::db::DBPoolWrapper;
+use crate::pathutils::Paths;
+use crate::pdf::{pdfium, PdfError};
+use crate::pdf_worker::{run_pdf_task, PdfTask};
+use crate::storage::{Storage, StorageBackend, StorageError};
+use crate::upload::TempUpload;
+
+/// Number of pages rendered for the page strip
+pub const PREVIEW_PAGES: usize = 5;
+const PREVIEW_JPEG_QUALITY: u8 = 85;
+const PREVIEW_AVIF_QUALITY: u8 = 70;
+/// Fastest AVIF encoder setting, previews are rendered often and are small anyway
+const PREVIEW_AVIF_SPEED: u8 = 10;
+/// Pages with more objects than this are too expensive to render
+const MAX_PAGE_OBJECTS: usize = 100_000;
+/// How long rendering, encoding and saving the previews of a note may take
+const RENDER_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewSize {
+    Thumbnail,
+    Medium,
+}
+
+impl PreviewSize {
+    pub const ALL: [PreviewSize; 2] = [PreviewSize::Thumbnail, PreviewSize::Medium];
+
+    /// Width of the preview image in pixels
+    fn width(&self) -> Pixels {
+        match self {
+            PreviewSize::Thumbnail => 200,
+            PreviewSize::Medium => 800,
+        }
+    }
+
+    /// Previews of very tall pages are scaled down to this height instead
+    fn max_height(&self) -> Pixels {
+        self.width() * 3
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            PreviewSize::Thumbnail => "thumbnail",
+            PreviewSize::Medium => "medium",
+        }
+    }
+}
+
+/// Formats compare in the same order as [`PreviewFormat::ALL`]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum PreviewFormat {
+    Avif,
+    Webp,
+    Jpeg,
+}
+
+impl PreviewFormat {
+    /// Formats in order of preference, JPEG is the fallback every client supports
+    pub const ALL: [PreviewFormat; 3] = [PreviewFormat::Avif, PreviewFormat::Webp, PreviewFormat::Jpeg];
+
+    fn extension(&self) -> &'static str {
+        match self {
+            PreviewFormat::Avif => "avif",
+            PreviewFormat::Webp => "webp",
+            PreviewFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PreviewError {
+    Pdf(PdfError),
+    Storage(StorageError),
+    Render(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for PreviewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PreviewError::Pdf(err) => write!(f, "{}", err),
+            PreviewError::Storage(err) => write!(f, "{}", err),
+            PreviewError::Render(msg) => write!(f, "Failed to render preview: {}", msg),
+            PreviewError::Io(err) => write!(f, "Failed to save preview: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for PreviewError {}
+
+impl From<PdfError> for PreviewError {
+    fn from(err: PdfError) -> Self {
+        PreviewError::Pdf(err)
+    }
+}
+
+impl From<StorageError> for PreviewError {
+    fn from(err: StorageError) -> Self {
+        PreviewError::Storage(err)
+    }
+}
+
+impl From<std::io::Error> for PreviewError {
+    fn from(err: std::io::Error) -> Self {
+        PreviewError::Io(err)
+    }
+}
+
+/// File name of a page preview, relative to the previews directory. Pages are numbered from 1.
+pub fn page_preview_file_name(note_id: Uuid, page: i32, size: PreviewSize, format: PreviewFormat) -> String {
+    format!("{}/{}-{}.{}", note_id, page, size.as_str(), format.extension())
+}
+
+/// File name of the single preview image of a note, relative to the previews directory.
+pub fn preview_file_name(note_id: Uuid) -> String {
+    format!("{}.jpg", note_id)
+}
+
+/// File names of all preview images of a note with previews of `preview_pages` pages.
+pub fn preview_file_names(note_id: Uuid, preview_pages: i32) -> Vec<String> {
+    let mut file_names = vec![preview_file_name(note_id)];
+    for page in 1..=preview_pages {
+        for size in PreviewSize::ALL {
+            for format in PreviewFormat::ALL {
+                file_names.push(page_preview_file_name(note_id, page, size, format));
+            }
+        }
+    }
+    file_names
+}
+
+/// Deletes all preview images of a note, ignoring ones that don't exist.
+pub async fn remove_previews(storage: &StorageBackend, paths: &Paths, note_id: Uuid) {
+    for file_name in preview_file_names(note_id, PREVIEW_PAGES as i32) {
+        if let Err(err) = storage.delete(&paths.get_preview_slug(&file_name)).await {
+            tracing::warn!("Failed to delete preview {}: {}", file_name, err);
+        }
+    }
+}
+
+/// Renders the first pages of a PDF at the medium size. The smaller sizes are scaled down from these.
+fn render_pages(pdf_path: &Path) -> Result<Vec<RgbImage>, PreviewError> {
+    let pdfium = pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(pdf_path, None)
+        .map_err(|err| PreviewError::Render(err.to_string()))?;
+
+    let config = PdfRenderConfig::new()
+        .set_target_width(PreviewSize::Medium.width())
+        .set_maximum_height(PreviewSize::Medium.max_height());
+    let images = document
+        .pages()
+        .iter()
+        .take(PREVIEW_PAGES)
+        .map(|page| {
+            if page.objects().len() > MAX_PAGE_OBJECTS {
+                return Err(PreviewError::Render("Page has too many objects to render".to_string()));
+            }
+            page.render_with_config(&config)
+                .map(|bitmap| bitmap.as_image().to_rgb8())
+                .map_err(|err| PreviewError::Render(err.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if images.is_empty() {
+        return Err(PreviewError::Render("PDF has no pages".to_string()));
+    }
+    Ok(images)
+}
+
+fn save_image(image: &RgbImage, format: PreviewFormat, path: &Path) -> Result<(), PreviewError> {
+    let writer = BufWriter::new(std::fs::File::create(path)?);
+    let (width, height) = image.dimensions();
+    let result = match format {
+        PreviewFormat::Avif => AvifEncoder::new_with_speed_quality(writer, PREVIEW_AVIF_SPEED, PREVIEW_AVIF_QUALITY)
+            .write_image(image, width, height, image::ExtendedColorType::Rgb8),
+        // The WebP encoder is lossless only, which suits mostly blank pages of text well
+        PreviewFormat::Webp => {
+            WebPEncoder::new_lossless(writer).write_image(image, width, height, image::ExtendedColorType::Rgb8)
+        }
+        PreviewFormat::Jpeg => JpegEncoder::new_with_quality(writer, PREVIEW_JPEG_QUALITY).encode_image(image),
+    };
+    result.map_err(|err| PreviewError::Render(err.to_string()))
+}
+
+fn save_previews(pages: Vec<RgbImage>, note_id: Uuid, previews_dir: &Path) -> Result<(), PreviewError> {
+    std::fs::create_dir_all(previews_dir.join(note_id.to_string()))?;
+
+    for (index, medium) in pages.into_iter().enumerate() {
+        let page = index as i32 + 1;
+        for size in PreviewSize::ALL {
+            let image = if size == PreviewSize::Medium {
+                medium.clone()
+            } else {
+                DynamicImage::ImageRgb8(medium.clone())
+                    .resize(
+                        size.width() as u32,
+                        size.max_height() as u32,
+                        image::imageops::FilterType::Lanczos3,
+                    )
+                    .to_rgb8()
+            };
+            for format in PreviewFormat::ALL {
+                save_image(&image, format, &previews_dir.join(page_preview_file_name(note_id, page, size, format)))?;
+            }
+        }
+
+        if page == 1 {
+            save_image(&medium, PreviewFormat::Jpeg, &previews_dir.join(preview_file_name(note_id)))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders the previews of a note into `previews_dir`, returning the number of pages rendered.
+/// This runs in a pdfium worker process.
+pub(crate) fn render_previews_blocking(pdf_path: &Path, note_id: Uuid, previews_dir: &Path) -> Result<i32, PreviewError> {
+    let pages = render_pages(pdf_path)?;
+    let page_count = pages.len() as i32;
+    save_previews(pages, note_id, previews_dir)?;
+    Ok(page_count)
+}
+
+/// Renders the previews of a note into `previews_dir` in a worker process, which is killed if it
+/// takes too long.
+async fn render_previews(pdf_path: &Path, note_id: Uuid, previews_dir: &Path) -> Result<i32, PreviewError> {
+    let task = PdfTask::RenderPreviews {
+        pdf_path: pdf_path.to_path_buf(),
+        note_id,
+        previews_dir: previews_dir.to_path_buf(),
+    };
+    Ok(run_pdf_task(task, RENDER_TIMEOUT).await?)
+}
+
+/// Renders the previews of a note from a local copy of its PDF and puts them into storage,
+/// returning the number of pages rendered.
+pub async fn generate_previews(
+    storage: &StorageBackend,
+    paths: &Paths,
+    note_id: Uuid,
+    pdf_path: &Path,
+) -> Result<i32, PreviewError> {
+    let work_dir = paths.get_previews_dir().join(format!(".render-{}", Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+
+    let result = async {
+        let preview_pages = render_previews(pdf_path, note_id, &work_dir).await?;
+        for file_name in preview_file_names(note_id, preview_pages) {
+            storage
+                .put(&paths.get_preview_slug(&file_name), &work_dir.join(&file_name))
+                .await?;
+        }
+        Ok(preview_pages)
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    result
+}
+
+/// Checks that every preview image a note is recorded as having is in storage.
+pub async fn previews_exist(
+    storage: &StorageBackend,
+    paths: &Paths,
+    note_id: Uuid,
+    preview_pages: i32,
+) -> Result<bool, StorageError> {
+    for file_name in preview_file_names(note_id, preview_pages) {
+        if !storage.exists(&paths.get_preview_slug(&file_name)).await? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[derive(Serialize, Debug)]
+pub struct PreviewFailure {
+    pub note_id: Uuid,
+    pub error: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PreviewBackfillReport {
+    pub dry_run: bool,
+    /// Number of notes checked
+    pub checked: usize,
+    /// Notes without a preview, or whose preview files are missing
+    pub missing: Vec<Uuid>,
+    pub regenerated: usize,
+    pub failed: Vec<PreviewFailure>,
+}
+
+async fn regenerate_previews(
+    db_wrapper: &DBPoolWrapper,
+    storage: &StorageBackend,
+    paths: &Paths,
+    status: &NotePreviewStatus,
+) -> Result<(), String> {
+    let pdf_key = paths.get_note_slug(&format!("{}.pdf", status.id));
+    let result = match TempUpload::from_storage(storage, &pdf_key, paths.get_notes_dir()).await {
+        Ok(pdf) => generate_previews(storage, paths, status.id, pdf.path()).await,
+        Err(err) => Err(err.into()),
+    };
+    match result {
+        Ok(preview_pages) => update_note_preview_status(db_wrapper, status.id, true, preview_pages)
+            .await
+            .map_err(|err| err.to_string()),
+        Err(err) => {
+            // Stop advertising previews whose files are gone
+            if status.has_preview_image {
+                update_note_preview_status(db_wrapper, status.id, false, 0)
+                    .await
+                    .map_err(|err| err.to_string())?;
+            }
+            Err(err.to_string())
+        }
+    }
+}
+
+/// Finds notes without previews, or whose preview files are missing from storage. Returns the
+/// number of notes checked along with the ones missing previews.
+pub async fn find_missing_previews(
+    db_wrapper: &DBPoolWrapper,
+    storage: &StorageBackend,
+    paths: &Paths,
+) -> Result<(usize, Vec<NotePreviewStatus>), sqlx::Error> {
+    let statuses = get_note_preview_statuses(db_wrapper).await?;
+    let checked = statuses.len();
+
+    let mut missing = Vec::new();
+    for status in statuses {
+        // Notes whose previews can't be checked are rendered again as well
+        let exists = previews_exist(storage, paths, status.id, status.preview_pages).await;
+        if !status.has_preview_image || !exists.unwrap_or(false) {
+            missing.push(status);
+        }
+    }
+
+    Ok((checked, missing))
+}
+
+/// Finds notes that are missing previews and renders them again, at most `concurrency` at a time.
+/// Nothing is rendered on a dry run.
+pub async fn backfill_previews(
+    db_wrapper: &DBPoolWrapper,
+    storage: &StorageBackend,
+    paths: &Paths,
+    concurrency: usize,
+    dry_run: bool,
+) -> Result<PreviewBackfillReport, sqlx::Error> {
+    let (checked, missing) = find_missing_previews(db_wrapper, storage, paths).await?;
+
+    let mut report = PreviewBackfillReport {
+        dry_run,
+        checked,
+        missing: missing.iter().map(|status| status.id).collect(),
+        regenerated: 0,
+        failed: Vec::new(),
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    let mut queue = missing.into_iter();
+    let mut tasks = JoinSet::new();
+    loop {
+        while tasks.len() < concurrency.max(1) {
+            let Some(status) = queue.next() else { break };
+            let (db_wrapper, storage, paths) = (db_wrapper.clone(), storage.clone(), paths.clone());
+            tasks.spawn(async move {
+                (status.id, regenerate_previews(&db_wrapper, &storage, &paths, &status).await)
+            });
+        }
+
+        match tasks.join_next().await {
+            Some(Ok((_, Ok(())))) => report.regenerated += 1,
+            Some(Ok((note_id, Err(error)))) => report.failed.push(PreviewFailure { note_id, error }),
+            Some(Err(err)) => tracing::error!("Preview backfill task failed: {}", err),
+            None => break,
+        }
+    }
+
+    Ok(report)
+}
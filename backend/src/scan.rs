@@ -0,0 +1,256 @@
+//! Malware scanning of uploaded files.
+//!
+//! Uploads are streamed to clamd with the `INSTREAM` command before a note is created for them.
+//! clamd is reached through `CLAMD_ADDRESS`, either the path of its Unix socket or `host:port`
+//! for TCP. Without it, files are not scanned at all.
+
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpStream, UnixStream};
+
+/// Size of the chunks files are streamed to clamd in
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Longest a scan may take, including connecting to clamd
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+/// Replies are a single short line, anything longer is not clamd
+const MAX_REPLY_LENGTH: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanResult {
+    Clean,
+    /// Malware was found, with the name of the signature that matched
+    Infected(String),
+}
+
+#[derive(Debug)]
+pub enum ScanError {
+    Io(std::io::Error),
+    /// clamd replied with an error, e.g. because the file is larger than its `StreamMaxLength`
+    Clamd(String),
+    Timeout,
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScanError::Io(err) => write!(f, "Failed to talk to clamd: {}", err),
+            ScanError::Clamd(msg) => write!(f, "clamd failed to scan the file: {}", msg),
+            ScanError::Timeout => write!(f, "Scanning took longer than {} seconds", SCAN_TIMEOUT.as_secs()),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+impl From<std::io::Error> for ScanError {
+    fn from(err: std::io::Error) -> Self {
+        ScanError::Io(err)
+    }
+}
+
+pub trait Scanner {
+    /// Scans the local file at `path` for malware.
+    fn scan(&self, path: &Path) -> impl Future<Output = Result<ScanResult, ScanError>> + Send;
+}
+
+/// Scans files with a clamd daemon.
+#[derive(Clone, Debug)]
+pub struct ClamdScanner {
+    address: String,
+}
+
+impl ClamdScanner {
+    /// `address` is the path of clamd's Unix socket, or `host:port` to connect over TCP.
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+
+    async fn scan_file(&self, path: &Path) -> Result<ScanResult, ScanError> {
+        if self.address.starts_with('/') {
+            instream(UnixStream::connect(&self.address).await?, path).await
+        } else {
+            instream(TcpStream::connect(&self.address).await?, path).await
+        }
+    }
+}
+
+impl Scanner for ClamdScanner {
+    async fn scan(&self, path: &Path) -> Result<ScanResult, ScanError> {
+        tokio::time::timeout(SCAN_TIMEOUT, self.scan_file(path))
+            .await
+            .map_err(|_| ScanError::Timeout)?
+    }
+}
+
+/// Streams a file to clamd with the `INSTREAM` command and reads the verdict.
+///
+/// The file is sent in chunks, each prefixed with its length as a big-endian `u32`, and ended
+/// with an empty chunk. clamd replies with `stream: OK`, `stream: {signature} FOUND` or an error.
+async fn instream<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S, path: &Path) -> Result<ScanResult, ScanError> {
+    // The `z` prefix makes commands and replies null-terminated
+    stream.write_all(b"zINSTREAM\0").await?;
+
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let length = file.read(&mut chunk).await?;
+        stream.write_all(&(length as u32).to_be_bytes()).await?;
+        if length == 0 {
+            break;
+        }
+        stream.write_all(&chunk[..length]).await?;
+    }
+    stream.flush().await?;
+
+    let mut reply = Vec::new();
+    BufReader::new(stream)
+        .take(MAX_REPLY_LENGTH as u64)
+        .read_until(0, &mut reply)
+        .await?;
+    parse_reply(String::from_utf8_lossy(&reply).trim_end_matches('\0').trim())
+}
+
+fn parse_reply(reply: &str) -> Result<ScanResult, ScanError> {
+    let verdict = reply.strip_prefix("stream:").map(str::trim).unwrap_or(reply);
+    if verdict == "OK" {
+        Ok(ScanResult::Clean)
+    } else if let Some(signature) = verdict.strip_suffix(" FOUND") {
+        Ok(ScanResult::Infected(signature.to_string()))
+    } else {
+        Err(ScanError::Clamd(reply.to_string()))
+    }
+}
+
+/// The scanner configured in [`EnvVars`](crate::env::EnvVars).
+#[derive(Clone, Debug, Default)]
+pub enum ScannerBackend {
+    Clamd(ClamdScanner),
+    /// Used when clamd isn't configured, every file is clean
+    #[default]
+    Disabled,
+}
+
+impl Scanner for ScannerBackend {
+    async fn scan(&self, path: &Path) -> Result<ScanResult, ScanError> {
+        match self {
+            ScannerBackend::Clamd(scanner) => scanner.scan(path).await,
+            ScannerBackend::Disabled => Ok(ScanResult::Clean),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tokio::io::{duplex, DuplexStream};
+    use uuid::Uuid;
+
+    /// A file in the temporary directory, removed when dropped.
+    struct TestFile(PathBuf);
+
+    impl TestFile {
+        async fn new(contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("scan-test-{}", Uuid::new_v4()));
+            tokio::fs::write(&path, contents).await.unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// What the fake clamd received for one `INSTREAM` command.
+    struct Received {
+        command: Vec<u8>,
+        chunks: Vec<Vec<u8>>,
+    }
+
+    /// Reads one `INSTREAM` command the way clamd does, up to the empty chunk, and sends `reply`.
+    async fn fake_clamd(mut stream: DuplexStream, reply: &'static [u8]) -> Received {
+        let mut command = vec![0; b"zINSTREAM\0".len()];
+        stream.read_exact(&mut command).await.unwrap();
+
+        let mut chunks = Vec::new();
+        loop {
+            let mut length = [0; 4];
+            stream.read_exact(&mut length).await.unwrap();
+            let length = u32::from_be_bytes(length) as usize;
+            if length == 0 {
+                break;
+            }
+            let mut chunk = vec![0; length];
+            stream.read_exact(&mut chunk).await.unwrap();
+            chunks.push(chunk);
+        }
+
+        stream.write_all(reply).await.unwrap();
+        Received { command, chunks }
+    }
+
+    async fn scan_with_reply(contents: &[u8], reply: &'static [u8]) -> (Result<ScanResult, ScanError>, Received) {
+        let file = TestFile::new(contents).await;
+        let (client, server) = duplex(16 * 1024);
+        let clamd = tokio::spawn(fake_clamd(server, reply));
+        let result = instream(client, &file.0).await;
+        (result, clamd.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn streams_file_in_length_prefixed_chunks() {
+        let contents = (0..CHUNK_SIZE * 2 + 100).map(|i| i as u8).collect::<Vec<_>>();
+        let (result, received) = scan_with_reply(&contents, b"stream: OK\0").await;
+
+        assert_eq!(result.unwrap(), ScanResult::Clean);
+        assert_eq!(received.command, b"zINSTREAM\0");
+        assert_eq!(
+            received.chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            [CHUNK_SIZE, CHUNK_SIZE, 100]
+        );
+        assert_eq!(received.chunks.concat(), contents);
+    }
+
+    #[tokio::test]
+    async fn empty_file_sends_only_terminator() {
+        let (result, received) = scan_with_reply(b"", b"stream: OK\0").await;
+
+        assert_eq!(result.unwrap(), ScanResult::Clean);
+        assert!(received.chunks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_found_signature() {
+        let (result, _) = scan_with_reply(b"X5O!P%@AP", b"stream: Eicar-Test-Signature FOUND\0").await;
+
+        assert_eq!(result.unwrap(), ScanResult::Infected("Eicar-Test-Signature".to_string()));
+    }
+
+    #[tokio::test]
+    async fn error_reply_is_an_error() {
+        let (result, _) = scan_with_reply(b"data", b"INSTREAM size limit exceeded. ERROR\0").await;
+
+        match result {
+            Err(ScanError::Clamd(msg)) => assert_eq!(msg, "INSTREAM size limit exceeded. ERROR"),
+            other => panic!("Expected a clamd error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn closed_connection_is_an_error() {
+        let (result, _) = scan_with_reply(b"data", b"").await;
+
+        assert!(matches!(result, Err(ScanError::Clamd(_))));
+    }
+
+    #[tokio::test]
+    async fn disabled_scanner_is_always_clean() {
+        let file = TestFile::new(b"X5O!P%@AP").await;
+
+        assert_eq!(ScannerBackend::Disabled.scan(&file.0).await.unwrap(), ScanResult::Clean);
+    }
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+use super::{Storage, StorageError};
+
+/// Stores files on the local disk, under the directory served by the static files server.
+#[derive(Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+    /// URL the static files server serves `root` at
+    base_url: Url,
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from("./static_files"),
+            base_url: Url::parse("http://localhost:3000").expect("Default localhost URL should be valid"),
+        }
+    }
+}
+
+impl LocalStorage {
+    pub fn new(root: PathBuf, base_url: Url) -> Self {
+        Self { root, base_url }
+    }
+
+    /// The path the file at `key` is stored at.
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+impl Storage for LocalStorage {
+    async fn put(&self, key: &str, source: &Path) -> Result<(), StorageError> {
+        let path = self.path(key);
+        if let Some(parent_dir) = path.parent() {
+            tokio::fs::create_dir_all(parent_dir).await?;
+        }
+
+        // Uploads are written next to the stored files, so this is usually an atomic rename
+        if tokio::fs::rename(source, &path).await.is_err() {
+            tokio::fs::copy(source, &path).await?;
+            let _ = tokio::fs::remove_file(source).await;
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str, destination: &Path) -> Result<(), StorageError> {
+        match tokio::fs::copy(self.path(key), destination).await {
+            Ok(_) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Err(StorageError::NotFound(key.to_string())),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path(key)).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        Ok(tokio::fs::try_exists(self.path(key)).await?)
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        let mut keys = Vec::new();
+        let mut dirs = vec![self.path(prefix)];
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(path);
+                } else if let Ok(key) = path.strip_prefix(&self.root) {
+                    let segments = key.iter().map(|segment| segment.to_string_lossy()).collect::<Vec<_>>();
+                    keys.push(segments.join("/"));
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Files on disk are served publicly by the static files server, so the URL never expires.
+    fn presign(&self, key: &str, _expires_in: Duration) -> String {
+        self.base_url
+            .join(key)
+            .map(String::from)
+            .unwrap_or_else(|_| format!("{}/{}", self.base_url.as_str().trim_end_matches('/'), key))
+    }
+}
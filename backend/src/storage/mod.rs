@@ -0,0 +1,127 @@
+//! Storage of note files and previews.
+//!
+//! Files are addressed by keys, the slugs built by [`Paths`](crate::pathutils::Paths) such as
+//! `notes/uploaded/{id}.pdf`. Processing always happens on local files, so files are moved into
+//! storage with [`Storage::put`] once they are complete and downloaded with [`Storage::get`]
+//! when they are needed again.
+//!
+//! The backend is picked with `STORAGE_BACKEND`: `local` keeps files on disk under the static
+//! files directory, and `s3` stores them in a bucket on any S3-compatible service.
+
+mod local;
+mod s3;
+
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+
+pub use self::local::LocalStorage;
+pub use self::s3::S3Storage;
+
+#[derive(Debug)]
+pub enum StorageError {
+    NotFound(String),
+    /// The storage service rejected the request or couldn't be reached
+    Request(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::NotFound(key) => write!(f, "File not found in storage: {}", key),
+            StorageError::Request(msg) => write!(f, "Storage request failed: {}", msg),
+            StorageError::Io(err) => write!(f, "Storage I/O failed: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<std::io::Error> for StorageError {
+    fn from(err: std::io::Error) -> Self {
+        StorageError::Io(err)
+    }
+}
+
+pub trait Storage {
+    /// Moves the local file at `source` into storage under `key`, replacing any existing file.
+    fn put(&self, key: &str, source: &Path) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Downloads the file at `key` to the local file `destination`.
+    fn get(&self, key: &str, destination: &Path) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    /// Deletes the file at `key`. Deleting a file that doesn't exist is not an error.
+    fn delete(&self, key: &str) -> impl Future<Output = Result<(), StorageError>> + Send;
+
+    fn exists(&self, key: &str) -> impl Future<Output = Result<bool, StorageError>> + Send;
+
+    /// Keys of all files under the directory `prefix`, in no particular order.
+    fn list(&self, prefix: &str) -> impl Future<Output = Result<Vec<String>, StorageError>> + Send;
+
+    /// A URL the file at `key` can be downloaded from, valid for at least `expires_in`.
+    fn presign(&self, key: &str, expires_in: Duration) -> String;
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    Local,
+    S3,
+}
+
+/// The storage backend configured in [`EnvVars`](crate::env::EnvVars).
+#[derive(Clone)]
+pub enum StorageBackend {
+    Local(LocalStorage),
+    S3(S3Storage),
+}
+
+impl Default for StorageBackend {
+    fn default() -> Self {
+        StorageBackend::Local(LocalStorage::default())
+    }
+}
+
+impl Storage for StorageBackend {
+    async fn put(&self, key: &str, source: &Path) -> Result<(), StorageError> {
+        match self {
+            StorageBackend::Local(storage) => storage.put(key, source).await,
+            StorageBackend::S3(storage) => storage.put(key, source).await,
+        }
+    }
+
+    async fn get(&self, key: &str, destination: &Path) -> Result<(), StorageError> {
+        match self {
+            StorageBackend::Local(storage) => storage.get(key, destination).await,
+            StorageBackend::S3(storage) => storage.get(key, destination).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match self {
+            StorageBackend::Local(storage) => storage.delete(key).await,
+            StorageBackend::S3(storage) => storage.delete(key).await,
+        }
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, StorageError> {
+        match self {
+            StorageBackend::Local(storage) => storage.exists(key).await,
+            StorageBackend::S3(storage) => storage.exists(key).await,
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
+        match self {
+            StorageBackend::Local(storage) => storage.list(prefix).await,
+            StorageBackend::S3(storage) => storage.list(prefix).await,
+        }
+    }
+
+    fn presign(&self, key: &str, expires_in: Duration) -> String {
+        match self {
+            StorageBackend::Local(storage) => storage.presign(key, expires_in),
+            StorageBackend::S3(storage) => storage.presign(key, expires_in),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Streaming of uploaded files to disk.
+//!
+//! Files are written chunk by chunk to a temporary file in the notes directory, hashed and size
+//! checked on the way, and then moved into storage. With local storage that is an atomic rename.
+//! The temporary file is removed if the upload is dropped before being persisted.
+//!
+//! Resumable uploads append chunks to a partial file per upload session instead, which is kept
+//! until the session is completed, aborted or expires.
+
+use axum::extract::multipart::{Field, MultipartError};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use crate::db::handlers::uploads::delete_expired_upload_sessions;
+use crate::db::DBPoolWrapper;
+use crate::storage::{Storage, StorageBackend, StorageError};
+
+/// How often expired upload sessions are cleaned up
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug)]
+pub enum UploadError {
+    /// The file is larger than the allowed size
+    TooLarge,
+    Read(MultipartError),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::TooLarge => write!(f, "File is too large"),
+            UploadError::Read(err) => write!(f, "Failed to read uploaded file: {}", err),
+            UploadError::Io(err) => write!(f, "Failed to write uploaded file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+impl From<std::io::Error> for UploadError {
+    fn from(err: std::io::Error) -> Self {
+        UploadError::Io(err)
+    }
+}
+
+/// An uploaded file in a temporary location.
+pub struct TempUpload {
+    path: PathBuf,
+    /// Hex-encoded SHA-256 of the file
+    pub sha256: String,
+    pub size: usize,
+    persisted: bool,
+    /// Whether the file is kept when the upload is dropped without being persisted
+    keep_on_drop: bool,
+}
+
+impl TempUpload {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Hashes the assembled file of an upload session. The file is kept if the upload is dropped,
+    /// so that a failed completion can be retried.
+    pub async fn from_session_file(path: PathBuf) -> Result<Self, std::io::Error> {
+        Self::hash_file(path, true).await
+    }
+
+    /// Hashes a file generated from an upload, e.g. by converting it to a PDF.
+    pub async fn from_file(path: PathBuf) -> Result<Self, std::io::Error> {
+        Self::hash_file(path, false).await
+    }
+
+    async fn hash_file(path: PathBuf, keep_on_drop: bool) -> Result<Self, std::io::Error> {
+        let (sha256, size) = sha256_file(&path).await?;
+
+        Ok(Self {
+            path,
+            sha256,
+            size,
+            persisted: false,
+            keep_on_drop,
+        })
+    }
+
+    /// Downloads a stored file to a temporary file in `dir`, e.g. to process a note's PDF.
+    pub async fn from_storage(storage: &StorageBackend, key: &str, dir: &Path) -> Result<Self, StorageError> {
+        let path = temp_file_path(dir);
+        storage.get(key, &path).await?;
+        let upload = Self::from_file(path.clone()).await;
+        if upload.is_err() {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+        Ok(upload?)
+    }
+
+    /// Moves the file into storage under `key`.
+    pub async fn persist(mut self, storage: &StorageBackend, key: &str) -> Result<(), StorageError> {
+        storage.put(key, &self.path).await?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if !self.persisted && !self.keep_on_drop {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// A new, unique path for a temporary upload file in `dir`.
+pub fn temp_file_path(dir: &Path) -> PathBuf {
+    dir.join(format!(".upload-{}.part", Uuid::new_v4()))
+}
+
+/// Streams a multipart file field into a temporary file in `dir`, failing once it exceeds `size_limit` bytes.
+pub async fn stream_to_temp_file(
+    mut field: Field<'_>,
+    dir: &Path,
+    size_limit: usize,
+) -> Result<TempUpload, UploadError> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    let mut upload = TempUpload {
+        path: temp_file_path(dir),
+        sha256: String::new(),
+        size: 0,
+        persisted: false,
+        keep_on_drop: false,
+    };
+    let mut file = tokio::fs::File::create(&upload.path).await?;
+    let mut hasher = Sha256::new();
+
+    while let Some(chunk) = field.chunk().await.map_err(UploadError::Read)? {
+        upload.size += chunk.len();
+        if upload.size > size_limit {
+            return Err(UploadError::TooLarge);
+        }
+        hasher.update(&chunk);
+        file.write_all(&chunk).await?;
+    }
+    file.sync_all().await?;
+
+    upload.sha256 = format!("{:x}", hasher.finalize());
+    Ok(upload)
+}
+
+/// The directory holding the partial files of upload sessions. It is inside the notes directory
+/// so that completed files can be renamed into place.
+pub fn sessions_dir(notes_dir: &Path) -> PathBuf {
+    notes_dir.join(".sessions")
+}
+
+/// The partial file of an upload session.
+pub fn session_file_path(notes_dir: &Path, session_id: Uuid) -> PathBuf {
+    sessions_dir(notes_dir).join(format!("{}.part", session_id))
+}
+
+/// Writes a chunk at `offset` in an upload session's partial file.
+///
+/// Anything past `offset` is discarded first, which is left over from a chunk that was written
+/// but never acknowledged.
+pub async fn write_session_chunk(path: &Path, offset: u64, chunk: &[u8]) -> Result<(), std::io::Error> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .await?;
+    file.set_len(offset).await?;
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    file.write_all(chunk).await?;
+    file.sync_all().await?;
+
+    Ok(())
+}
+
+/// Periodically deletes expired upload sessions along with their partial files.
+pub async fn cleanup_expired_sessions(db_wrapper: DBPoolWrapper, notes_dir: PathBuf) {
+    let mut interval = tokio::time::interval(SESSION_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        match delete_expired_upload_sessions(&db_wrapper).await {
+            Ok(session_ids) => {
+                for session_id in &session_ids {
+                    let _ = tokio::fs::remove_file(session_file_path(&notes_dir, *session_id)).await;
+                }
+                if !session_ids.is_empty() {
+                    tracing::info!("Removed {} expired upload sessions", session_ids.len());
+                }
+            }
+            Err(err) => tracing::error!("Failed to clean up expired upload sessions: {}", err),
+        }
+    }
+}
+
+/// Hashes a file, returning its hex encoded SHA-256 and size.
+pub async fn sha256_file(path: &Path) -> Result<(String, usize), std::io::Error> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        size += read;
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
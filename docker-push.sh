@@ -15,6 +15,13 @@ if [ -z "$GOOGLE_CLIENT_ID" ]; then
     exit 1
 fi
 
+# Check if PDFIUM_SHA256 is set
+if [ -z "$PDFIUM_SHA256" ]; then
+    echo "Error: PDFIUM_SHA256 environment variable is not set"
+    echo "Set it to the sha256 of pdfium-linux-musl-x64.tgz from the pdfium release pinned in the Dockerfile"
+    exit 1
+fi
+
 echo "Building and pushing $DOCKERHUB_USERNAME/$IMAGE_NAME:$TAG..."
 
 # Login to Docker Hub (interactive if no password set)
@@ -29,6 +36,7 @@ docker buildx build \
     --push \
     --tag "$DOCKERHUB_USERNAME/$IMAGE_NAME:$TAG" \
     --build-arg "VITE_GOOGLE_CLIENT_ID=$GOOGLE_CLIENT_ID" \
+    --build-arg "PDFIUM_SHA256=$PDFIUM_SHA256" \
     .
 
 echo "Done! Image pushed to $DOCKERHUB_USERNAME/$IMAGE_NAME:$TAG"
\ No newline at end of file
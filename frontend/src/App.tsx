@@ -41,7 +41,7 @@ const AppContent: React.FC = () => {
                 setNotes(fetchedNotes);
             } else {
                 const searchResults = await notesApi.searchNotes(query);
-                setNotes(searchResults);
+                setNotes(searchResults.notes);
             }
         } catch (err) {
             setError('Search failed. Please try again.');
@@ -1,4 +1,4 @@
-import type { ResponseNote, DBVote, VoteType } from "../types.ts";
+import type { ResponseNote, ResponseSearch, DBVote, VoteType, ProfessorSuggestion } from "../types.ts";
 import { authenticatedFetch } from "./authApi.ts";
 
 class NotesAPI {
@@ -29,11 +29,17 @@ class NotesAPI {
     }
 
     // GET /api/notes/search?query=query
-    async searchNotes(query: string): Promise<ResponseNote[]> {
+    async searchNotes(query: string): Promise<ResponseSearch> {
         const url = `/api/notes/search?query=${encodeURIComponent(query)}`;
         return this.fetchWithErrorHandling(url);
     }
 
+    // GET /api/professors/suggest?names=a,b - Existing professors similar to the names of an upload
+    async suggestProfessors(names: string): Promise<ProfessorSuggestion[]> {
+        const url = `/api/professors/suggest?names=${encodeURIComponent(names)}`;
+        return this.fetchWithErrorHandling(url);
+    }
+
     // POST /api/notes/:note_id/vote?vote_type=type - Vote on a note
     async voteOnNote(noteId: string, voteType: VoteType): Promise<DBVote | null> {
         const url = `/api/notes/${noteId}/vote?vote_type=${voteType}`;
@@ -2,7 +2,7 @@
 import React, { useState } from 'react';
 import { X, Upload, FileText, AlertCircle } from 'lucide-react';
 import { notesApi } from '../api/notesApi';
-import type { ResponseNote } from '../types';
+import type { ProfessorSuggestion, ResponseNote } from '../types';
 
 interface UploadModalProps {
   isOpen: boolean;
@@ -16,9 +16,15 @@ interface FormData {
   description: string;
   professorNames: string;
   tags: string;
-  file: File | null;
+  files: File[];
 }
 
+const DOCUMENT_EXTENSIONS = ['.pdf', '.docx', '.pptx'];
+const IMAGE_EXTENSIONS = ['.jpg', '.jpeg', '.png', '.heic'];
+const MAX_IMAGES = 50;
+
+const fileExtension = (file: File) => file.name.slice(file.name.lastIndexOf('.')).toLowerCase();
+
 const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess }) => {
   const [formData, setFormData] = useState<FormData>({
     courseName: '',
@@ -26,23 +32,53 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
     description: '',
     professorNames: '',
     tags: '',
-    file: null,
+    files: [],
   });
   const [isUploading, setIsUploading] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [dragActive, setDragActive] = useState(false);
+  const [professorSuggestions, setProfessorSuggestions] = useState<ProfessorSuggestion[]>([]);
 
   const handleInputChange = (field: keyof FormData, value: string) => {
     setFormData(prev => ({ ...prev, [field]: value }));
     setError(null);
   };
 
-  const handleFileChange = (file: File | null) => {
-    if (file && file.type !== 'application/pdf') {
-      setError('Only PDF files are supported');
+  // Names that don't match a professor exactly create a new one, so offer similar existing professors
+  const checkProfessorNames = async () => {
+    if (!formData.professorNames.trim()) {
+      setProfessorSuggestions([]);
+      return;
+    }
+    try {
+      const suggestions = await notesApi.suggestProfessors(formData.professorNames);
+      setProfessorSuggestions(suggestions.filter(s => !s.exact_match && s.candidates.length > 0));
+    } catch {
+      setProfessorSuggestions([]);
+    }
+  };
+
+  const replaceProfessorName = (name: string, replacement: string) => {
+    const names = formData.professorNames.split(',').map(n => (n.trim() === name ? replacement : n.trim()));
+    setFormData(prev => ({ ...prev, professorNames: names.filter(n => n).join(', ') }));
+    setProfessorSuggestions(prev => prev.filter(s => s.name !== name));
+  };
+
+  const handleFileChange = (files: File[]) => {
+    const isImage = (file: File) => IMAGE_EXTENSIONS.includes(fileExtension(file));
+    if (files.some(file => !isImage(file) && !DOCUMENT_EXTENSIONS.includes(fileExtension(file)))) {
+      setError('Only PDF, DOCX, PPTX, JPEG, PNG and HEIC files are supported');
+      return;
+    }
+    if (files.length > 1 && !files.every(isImage)) {
+      setError('Upload either a single document or one or more images');
+      return;
+    }
+    if (files.length > MAX_IMAGES) {
+      setError(`At most ${MAX_IMAGES} images can be uploaded at once`);
       return;
     }
-    setFormData(prev => ({ ...prev, file }));
+    setFormData(prev => ({ ...prev, files }));
     setError(null);
   };
 
@@ -60,8 +96,8 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
     e.preventDefault();
     e.stopPropagation();
     setDragActive(false);
-    if (e.dataTransfer.files && e.dataTransfer.files[0]) {
-      handleFileChange(e.dataTransfer.files[0]);
+    if (e.dataTransfer.files && e.dataTransfer.files.length > 0) {
+      handleFileChange(Array.from(e.dataTransfer.files));
     }
   };
 
@@ -74,8 +110,8 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
       setError('Course code is required');
       return false;
     }
-    if (!formData.file) {
-      setError('Please select a PDF file');
+    if (formData.files.length === 0) {
+      setError('Please select a file');
       return false;
     }
     return true;
@@ -95,12 +131,14 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
       if (formData.description.trim()) uploadFormData.append('description', formData.description.trim());
       if (formData.professorNames.trim()) uploadFormData.append('professor_names', formData.professorNames.trim());
       if (formData.tags.trim()) uploadFormData.append('tags', formData.tags.trim());
-      uploadFormData.append('file', formData.file!);
+      // Images are merged into one PDF in the order they were selected
+      formData.files.forEach(file => uploadFormData.append('file', file));
 
       const newNote = await notesApi.uploadNote(uploadFormData);
       onSuccess(newNote);
       onClose();
-      setFormData({ courseName: '', courseCode: '', description: '', professorNames: '', tags: '', file: null });
+      setFormData({ courseName: '', courseCode: '', description: '', professorNames: '', tags: '', files: [] });
+      setProfessorSuggestions([]);
     } catch (err) {
       setError(err instanceof Error ? err.message : 'Upload failed. Please try again.');
     } finally {
@@ -109,7 +147,8 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
   };
 
   const resetAndClose = () => {
-    setFormData({ courseName: '', courseCode: '', description: '', professorNames: '', tags: '', file: null });
+    setFormData({ courseName: '', courseCode: '', description: '', professorNames: '', tags: '', files: [] });
+    setProfessorSuggestions([]);
     setError(null);
     onClose();
   };
@@ -175,9 +214,28 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
                   type="text"
                   value={formData.professorNames}
                   onChange={(e) => handleInputChange('professorNames', e.target.value)}
+                  onBlur={checkProfessorNames}
                   className="w-full px-3 py-2 border border-border bg-surface rounded-lg focus:ring-2 focus:ring-primary focus:border-transparent"
                   placeholder="e.g., Dr. Smith, Prof. Johnson (comma-separated)"
               />
+              {professorSuggestions.map(suggestion => (
+                  <p key={suggestion.name} className="mt-2 text-sm text-text-muted">
+                    "{suggestion.name}" will be added as a new professor. Did you mean{' '}
+                    {suggestion.candidates.map((candidate, index) => (
+                        <React.Fragment key={candidate.id}>
+                          {index > 0 && ', '}
+                          <button
+                              type="button"
+                              onClick={() => replaceProfessorName(suggestion.name, candidate.name)}
+                              className="text-primary hover:underline"
+                          >
+                            {candidate.name}
+                          </button>
+                        </React.Fragment>
+                    ))}
+                    ?
+                  </p>
+              ))}
             </div>
 
             <div>
@@ -192,16 +250,18 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
             </div>
 
             <div>
-              <label className="block text-sm font-medium text-text-muted mb-2">PDF File *</label>
+              <label className="block text-sm font-medium text-text-muted mb-2">File *</label>
               <div
                   className={`border-2 border-dashed rounded-lg p-6 text-center transition-colors ${dragActive ? 'border-primary bg-gray-800' : 'border-border hover:border-primary'}`}
                   onDragEnter={handleDrag} onDragLeave={handleDrag} onDragOver={handleDrag} onDrop={handleDrop}
               >
-                {formData.file ? (
+                {formData.files.length > 0 ? (
                     <div className="flex items-center justify-center space-x-2 text-green-400">
                       <FileText size={20} />
-                      <span className="font-medium">{formData.file.name}</span>
-                      <button type="button" onClick={() => handleFileChange(null)} className="text-red-400 hover:text-red-300">
+                      <span className="font-medium">
+                        {formData.files.length === 1 ? formData.files[0].name : `${formData.files.length} images`}
+                      </span>
+                      <button type="button" onClick={() => handleFileChange([])} className="text-red-400 hover:text-red-300">
                         <X size={16} />
                       </button>
                     </div>
@@ -209,13 +269,19 @@ const UploadModal: React.FC<UploadModalProps> = ({ isOpen, onClose, onSuccess })
                     <>
                       <Upload size={40} className="mx-auto text-gray-500 mb-2" />
                       <p className="text-text-muted mb-2">
-                        Drag and drop your PDF file here, or{' '}
+                        Drag and drop your file here, or{' '}
                         <label className="text-primary cursor-pointer hover:underline">
                           browse
-                          <input type="file" accept=".pdf" onChange={(e) => handleFileChange(e.target.files?.[0] || null)} className="hidden" />
+                          <input
+                              type="file"
+                              multiple
+                              accept={[...DOCUMENT_EXTENSIONS, ...IMAGE_EXTENSIONS].join(',')}
+                              onChange={(e) => handleFileChange(Array.from(e.target.files || []))}
+                              className="hidden"
+                          />
                         </label>
                       </p>
-                      <p className="text-sm text-gray-500">Only PDF files are supported</p>
+                      <p className="text-sm text-gray-500">PDF, DOCX or PPTX, or photos of your notes (JPEG, PNG, HEIC)</p>
                     </>
                 )}
               </div>
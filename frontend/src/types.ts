@@ -17,6 +17,14 @@ export interface DBVote {
     created_at: string | null;
 }
 
+export interface ResponsePreview {
+    page: number;
+    size: 'thumbnail' | 'medium';
+    // JPEG, supported everywhere
+    url: string;
+    variants: Partial<Record<'avif' | 'webp', string>>;
+}
+
 export interface ResponseNote {
     id: string;
     course_name: string;
@@ -26,6 +34,7 @@ export interface ResponseNote {
     tags: string[];
     is_public: boolean;
     preview_image_url?: string;
+    previews: ResponsePreview[];
     file_url: string;
     uploader_user: ResponseUser;
     created_at: string;
@@ -35,6 +44,24 @@ export interface ResponseNote {
     downloads: number;
 }
 
+export interface ProfessorCandidate {
+    id: string;
+    name: string;
+    score: number;
+}
+
+export interface ProfessorSuggestion {
+    name: string;
+    exact_match: boolean;
+    candidates: ProfessorCandidate[];
+}
+
+export interface ResponseSearch {
+    notes: ResponseNote[];
+    fuzzy: boolean;
+    did_you_mean: string | null;
+}
+
 // Keep existing types for component props
 export interface SearchBarProps {
     searchQuery: string;